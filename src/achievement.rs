@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::palace::{Game, Moved};
+use crate::replay::Step;
+use crate::stats::win_tile;
+use crate::util;
+
+/// 检查成就的时机
+pub enum Event<'a> {
+    /// 移动了一步
+    Moved(&'a Game, &'a Moved),
+    /// 一局结束
+    Finished,
+}
+
+/// 一个成就，名字和说明见 `i18n::Messages::achievement`
+pub struct Achievement {
+    pub id: &'static str,
+    /// 达成所需的进度
+    pub target: u64,
+    /// 由事件和之前的进度得出新的进度
+    progress: fn(&Event, u64) -> u64,
+}
+
+/// “速战速决”的步数限制
+const QUICK_MOVES: u64 = 250;
+
+/// 全部成就，按界面上的顺序排列
+pub const ALL: [Achievement; 9] = [
+    Achievement {
+        id: "first_game",
+        target: 1,
+        progress: games,
+    },
+    Achievement {
+        id: "first_2048",
+        target: win_tile(4) as u64,
+        progress: first_2048,
+    },
+    Achievement {
+        id: "tile_4096",
+        target: 4096,
+        progress: tile_4096,
+    },
+    Achievement {
+        id: "perfect_3x3",
+        target: win_tile(3) as u64,
+        progress: perfect_3x3,
+    },
+    Achievement {
+        id: "quick_score",
+        target: 3000,
+        progress: quick_score,
+    },
+    Achievement {
+        id: "four_merges",
+        target: 4,
+        progress: four_merges,
+    },
+    Achievement {
+        id: "score_20000",
+        target: 20000,
+        progress: score_20000,
+    },
+    Achievement {
+        id: "games_10",
+        target: 10,
+        progress: games,
+    },
+    Achievement {
+        id: "games_100",
+        target: 100,
+        progress: games,
+    },
+];
+
+/// 完成的局数
+fn games(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Finished => progress + 1,
+        _ => progress,
+    }
+}
+
+/// 任意模式的最大数字
+fn first_2048(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(game, _) => progress.max(clamp(game.max_tile())),
+        _ => progress,
+    }
+}
+
+/// 4 * 4 模式的最大数字
+fn tile_4096(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(game, _) if game.palaces.len() == 4 => progress.max(clamp(game.max_tile())),
+        _ => progress,
+    }
+}
+
+/// 3 * 3 模式没有撤回过的一局的最大数字
+fn perfect_3x3(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(game, _) if game.palaces.len() == 3 && !game.steps.contains(&Step::Undo) => {
+            progress.max(clamp(game.max_tile()))
+        }
+        _ => progress,
+    }
+}
+
+/// 限定步数内的得分
+fn quick_score(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(game, _) if game.moves() <= QUICK_MOVES => {
+            progress.max(clamp(game.total_score))
+        }
+        _ => progress,
+    }
+}
+
+/// 一步中合并的次数
+fn four_merges(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(_, moved) => progress.max(moved.merges as u64),
+        _ => progress,
+    }
+}
+
+/// 单局得分
+fn score_20000(event: &Event, progress: u64) -> u64 {
+    match event {
+        Event::Moved(game, _) => progress.max(clamp(game.total_score)),
+        _ => progress,
+    }
+}
+
+fn clamp(value: u128) -> u64 {
+    value.min(u64::MAX as u128) as u64
+}
+
+/// 一个档案的成就进度
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Achievements {
+    /// 各成就的进度
+    pub progress: BTreeMap<String, u64>,
+    /// 已达成的成就和达成日期（Unix 秒）
+    pub unlocked: BTreeMap<String, u64>,
+}
+
+impl Achievements {
+    /// 成就的进度，不超过目标
+    pub fn progress(&self, achievement: &Achievement) -> u64 {
+        self.progress
+            .get(achievement.id)
+            .copied()
+            .unwrap_or(0)
+            .min(achievement.target)
+    }
+
+    /// 达成日期，未达成时为空
+    pub fn unlocked(&self, achievement: &Achievement) -> Option<u64> {
+        self.unlocked.get(achievement.id).copied()
+    }
+
+    /// 根据事件更新进度，返回新达成的成就
+    pub fn update(&mut self, event: &Event) -> Vec<&'static Achievement> {
+        let mut unlocked = Vec::new();
+        for achievement in ALL.iter() {
+            if self.unlocked.contains_key(achievement.id) {
+                continue;
+            }
+            let progress = (achievement.progress)(event, self.progress(achievement));
+            if progress == self.progress(achievement) {
+                continue;
+            }
+            self.progress
+                .insert(achievement.id.to_string(), progress.min(achievement.target));
+            if progress >= achievement.target {
+                self.unlocked
+                    .insert(achievement.id.to_string(), util::now());
+                unlocked.push(achievement);
+            }
+        }
+        unlocked
+    }
+
+    /// 合并导入的成就：进度取较大值，达成日期取较早的
+    pub fn merge(&mut self, other: &Achievements) {
+        for (id, &progress) in &other.progress {
+            let local = self.progress.entry(id.clone()).or_default();
+            *local = (*local).max(progress);
+        }
+        for (id, &date) in &other.unlocked {
+            let local = self.unlocked.entry(id.clone()).or_insert(date);
+            *local = (*local).min(date);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::palace::MoveDirection;
+
+    fn ids(unlocked: &[&Achievement]) -> Vec<&'static str> {
+        unlocked.iter().map(|a| a.id).collect()
+    }
+
+    #[test]
+    fn test_update() {
+        let mut achievements = Achievements::default();
+        let mut game = Game::with_seed(3, 1);
+        game.palaces = vec![vec![2, 2, 4], vec![4, 4, 256], vec![256, 0, 0]];
+        let moved = game.move_palaces(MoveDirection::Left);
+        assert_eq!(2, moved.merges);
+        assert!(achievements.update(&Event::Moved(&game, &moved)).is_empty());
+        assert_eq!(256, achievements.progress(&ALL[3]));
+
+        game.palaces = vec![vec![256, 256, 0], vec![2, 2, 0], vec![4, 4, 0]];
+        let moved = game.move_palaces(MoveDirection::Left);
+        assert_eq!(
+            vec!["perfect_3x3"],
+            ids(&achievements.update(&Event::Moved(&game, &moved)))
+        );
+        assert!(achievements.unlocked(&ALL[3]).is_some());
+
+        // 撤回过的一局不再计入
+        let mut other = Achievements::default();
+        game.steps.push(Step::Undo);
+        other.update(&Event::Moved(&game, &moved));
+        assert_eq!(0, other.progress(&ALL[3]));
+
+        assert_eq!(
+            vec!["first_game"],
+            ids(&achievements.update(&Event::Finished))
+        );
+        assert!(achievements.update(&Event::Finished).is_empty());
+        assert_eq!(2, achievements.progress(&ALL[7]));
+
+        other.progress.insert("games_10".to_string(), 5);
+        other.unlocked.insert("perfect_3x3".to_string(), 1);
+        achievements.merge(&other);
+        assert_eq!(5, achievements.progress(&ALL[7]));
+        assert_eq!(Some(1), achievements.unlocked(&ALL[3]));
+
+        let json = serde_json::to_string(&achievements).unwrap();
+        assert_eq!(achievements, serde_json::from_str(&json).unwrap());
+    }
+}
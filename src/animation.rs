@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::Widget;
+
+use crate::palace::{Moved, Slide};
+
+/// 两帧之间的间隔
+pub const FRAME: Duration = Duration::from_millis(30);
+
+/// 滑动占整个动画的比例，之后是合并的弹出和新数字的淡入
+const SLIDE: f64 = 0.6;
+
+/// 一次移动的动画
+pub struct Animation {
+    moved: Moved,
+    started: Instant,
+    duration: Duration,
+}
+
+/// 动画当前所处的阶段，`t` 为该阶段 0 到 1 的进度
+pub enum Phase<'a> {
+    /// 数字方块沿移动路径滑动
+    Slide { slides: &'a [Slide], t: f64 },
+    /// 合并出的数字弹出，新数字淡入
+    Settle {
+        merged: &'a [(usize, usize)],
+        spawned: Option<(usize, usize)>,
+        t: f64,
+    },
+}
+
+impl Animation {
+    pub fn new(moved: Moved, duration: Duration) -> Self {
+        Self {
+            moved,
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    /// 动画是否已经结束
+    pub fn finished(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+
+    /// 当前阶段
+    pub fn phase(&self) -> Phase<'_> {
+        self.phase_at(self.started.elapsed())
+    }
+
+    fn phase_at(&self, elapsed: Duration) -> Phase<'_> {
+        let t = (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0);
+        if t < SLIDE {
+            Phase::Slide {
+                slides: &self.moved.slides,
+                t: ease_out(t / SLIDE),
+            }
+        } else {
+            Phase::Settle {
+                merged: &self.moved.merged,
+                spawned: self.moved.spawned,
+                t: (t - SLIDE) / (1.0 - SLIDE),
+            }
+        }
+    }
+}
+
+/// 得分后 “+N” 浮起的时间
+pub const GAIN: Duration = Duration::from_millis(900);
+
+/// 打破最高分后庆祝的时间
+pub const CELEBRATION: Duration = Duration::from_millis(2500);
+
+/// 一段限时的效果，如得分浮起和打破最高分的庆祝
+pub struct Effect<T> {
+    pub value: T,
+    started: Instant,
+    duration: Duration,
+}
+
+impl<T> Effect<T> {
+    pub fn new(value: T, duration: Duration) -> Self {
+        Self {
+            value,
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    /// 效果是否已经结束
+    pub fn finished(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+
+    /// 0 到 1 的进度
+    pub fn progress(&self) -> f64 {
+        (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+}
+
+/// 庆祝时洒在空白处的彩纸，`seed` 相同时位置相同
+pub struct Confetti {
+    seed: u64,
+    /// 空白格子中有彩纸的比例
+    density: f64,
+}
+
+impl Confetti {
+    const PIECES: [&'static str; 6] = ["*", "+", "·", "•", "✦", "°"];
+    const COLORS: [Color; 6] = [
+        Color::LightRed,
+        Color::LightYellow,
+        Color::LightGreen,
+        Color::LightCyan,
+        Color::LightBlue,
+        Color::LightMagenta,
+    ];
+
+    pub fn new(seed: u64, density: f64) -> Self {
+        Self { seed, density }
+    }
+}
+
+impl Widget for Confetti {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                // 每一格都取随机数，使同一个种子下彩纸的位置不随画面内容变化
+                let (roll, piece, color) = (
+                    rng.gen::<f64>(),
+                    rng.gen_range(0..Self::PIECES.len()),
+                    rng.gen_range(0..Self::COLORS.len()),
+                );
+                let cell = buf.get_mut(x, y);
+                if roll < self.density && cell.symbol == " " {
+                    cell.set_symbol(Self::PIECES[piece])
+                        .set_style(Style::default().fg(Self::COLORS[color]));
+                }
+            }
+        }
+    }
+}
+
+/// 先快后慢
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// 两个格子之间按进度插值的位置
+pub fn lerp(from: Rect, to: Rect, t: f64) -> Rect {
+    let mix = |a: u16, b: u16| (a as f64 + (b as f64 - a as f64) * t).round() as u16;
+    Rect::new(
+        mix(from.x, to.x),
+        mix(from.y, to.y),
+        mix(from.width, to.width),
+        mix(from.height, to.height),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_phase() {
+        let moved = Moved {
+            merged: vec![(0, 0)],
+            ..Moved::default()
+        };
+        let animation = Animation::new(moved, Duration::from_millis(100));
+        assert!(matches!(
+            animation.phase_at(Duration::from_millis(0)),
+            Phase::Slide { t, .. } if t == 0.0
+        ));
+        assert!(matches!(
+            animation.phase_at(Duration::from_millis(80)),
+            Phase::Settle { merged: [(0, 0)], t, .. } if (t - 0.5).abs() < 1e-9
+        ));
+        assert!(matches!(
+            animation.phase_at(Duration::from_secs(1)),
+            Phase::Settle { t, .. } if t == 1.0
+        ));
+
+        let effect = Effect::new(10, Duration::from_secs(60));
+        assert!(!effect.finished());
+        assert!(effect.progress() < 0.1);
+        let effect = Effect::new(10, Duration::ZERO);
+        assert!(effect.finished());
+        assert_eq!(1.0, effect.progress());
+
+        let from = Rect::new(0, 0, 10, 4);
+        let to = Rect::new(30, 8, 10, 4);
+        assert_eq!(Rect::new(15, 4, 10, 4), lerp(from, to, 0.5));
+        assert_eq!(to, lerp(from, to, 1.0));
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::achievement::Achievements;
+use crate::config::{self, Config, PALACE_SIZES};
+use crate::global::META_KEYS;
+use crate::i18n;
+use crate::leaderboard::{self, Entry};
+use crate::palace::Game;
+use crate::replay::Replay;
+use crate::schema::{self, SCHEMA_VERSION};
+use crate::stats::Stats;
+use crate::storage::Storage;
+use crate::util;
+
+/// 可以在不同机器之间迁移的全部数据
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Archive {
+    /// 数据版本
+    pub version: u32,
+    /// 导出时间（Unix 秒）
+    pub exported_at: u64,
+    /// 各模式的最高分
+    pub top_scores: BTreeMap<usize, u128>,
+    /// 各模式的统计
+    pub stats: BTreeMap<usize, Stats>,
+    /// 各模式的排行榜
+    pub leaderboards: BTreeMap<usize, Vec<Entry>>,
+    /// 成就进度
+    pub achievements: Achievements,
+    /// 撤回用的历史记录
+    pub history: Vec<Game>,
+    /// 未完成的存档
+    pub save: Option<Replay>,
+    /// 最近一局的回放
+    pub replay: Option<Replay>,
+    /// 配置文件内容
+    pub settings: Option<String>,
+    /// 元数据，如上次输入的名字
+    pub meta: BTreeMap<String, String>,
+}
+
+/// 导入时各项数据的处理结果
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// 更新了最高分的模式
+    pub top_scores: Vec<usize>,
+    /// 导入了统计的模式
+    pub stats: Vec<usize>,
+    /// 排行榜有变化的模式
+    pub leaderboards: Vec<usize>,
+    pub achievements: bool,
+    pub history: bool,
+    pub save: bool,
+    pub replay: bool,
+}
+
+impl Archive {
+    /// 读取存储中的数据，不包含配置文件
+    pub fn from_storage(storage: &dyn Storage) -> Result<Self> {
+        let top_scores = PALACE_SIZES
+            .iter()
+            .map(|&size| Ok((size, storage.top_score(size)?)))
+            .filter(|score| !matches!(score, Ok((_, 0))))
+            .collect::<Result<_>>()?;
+        let stats = PALACE_SIZES
+            .iter()
+            .map(|&size| Ok((size, storage.stats(size)?)))
+            .filter(|stats| !matches!(stats, Ok((_, s)) if s.games == 0))
+            .collect::<Result<_>>()?;
+        let leaderboards = PALACE_SIZES
+            .iter()
+            .map(|&size| Ok((size, storage.leaderboard(size)?)))
+            .filter(|entries| !matches!(entries, Ok((_, e)) if e.is_empty()))
+            .collect::<Result<_>>()?;
+        let meta = META_KEYS
+            .iter()
+            .filter_map(|&key| match storage.meta(key) {
+                Ok(value) => value.map(|value| Ok((key.to_string(), value))),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            version: SCHEMA_VERSION,
+            exported_at: 0,
+            top_scores,
+            stats,
+            leaderboards,
+            achievements: storage.achievements()?,
+            history: storage.histories()?,
+            save: storage.save()?,
+            replay: storage.replay()?,
+            settings: None,
+            meta,
+        })
+    }
+
+    /// 导出存储中的数据和配置文件
+    pub fn export(storage: &dyn Storage) -> Result<Self> {
+        let mut archive = Self::from_storage(storage)?;
+        archive.exported_at = util::now();
+        archive.settings = match config::get().settings_path() {
+            Some(path) if path.exists() => Some(fs::read_to_string(path)?),
+            _ => None,
+        };
+        Ok(archive)
+    }
+
+    /// 读取导出的文件，旧版本的文件会先升级
+    pub fn load(path: &Path) -> Result<Self> {
+        let messages = i18n::messages();
+        let content = fs::read(path)
+            .with_context(|| format!("{}{}", messages.read_failed, path.display()))?;
+        let mut value = serde_json::from_slice(&content)
+            .with_context(|| format!("{}{}", messages.malformed, path.display()))?;
+        schema::migrate_json(&mut value)
+            .with_context(|| format!("{}{}", messages.upgrade_failed, path.display()))?;
+        serde_json::from_value(value)
+            .with_context(|| format!("{}{}", messages.malformed, path.display()))
+    }
+
+    /// 写入存储。`replace` 为真时覆盖现有数据，否则合并：
+    /// 最高分取较大值，排行榜合并后保留前几名，成就进度取较大值，统计相加，
+    /// 其它数据只在本地没有时导入
+    pub fn import_into(&self, storage: &dyn Storage, replace: bool) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for &size in PALACE_SIZES.iter() {
+            let score = self.top_scores.get(&size).copied().unwrap_or(0);
+            let local = storage.top_score(size)?;
+            if score > local || (replace && score != local) {
+                storage.insert_top_score(size, score)?;
+                report.top_scores.push(size);
+            }
+            let stats = self.stats.get(&size).cloned().unwrap_or_default();
+            let local = storage.stats(size)?;
+            let stats = if replace {
+                stats
+            } else {
+                let mut merged = local.clone();
+                merged.merge(&stats);
+                merged
+            };
+            if stats != local {
+                storage.insert_stats(size, &stats)?;
+                report.stats.push(size);
+            }
+            let entries = self.leaderboards.get(&size).cloned().unwrap_or_default();
+            let local = storage.leaderboard(size)?;
+            let entries = if replace {
+                entries
+            } else {
+                leaderboard::merge(&local, &entries)
+            };
+            if entries != local {
+                storage.insert_leaderboard(size, &entries)?;
+                report.leaderboards.push(size);
+            }
+        }
+        let local = storage.achievements()?;
+        let achievements = if replace {
+            self.achievements.clone()
+        } else {
+            let mut merged = local.clone();
+            merged.merge(&self.achievements);
+            merged
+        };
+        if achievements != local {
+            storage.insert_achievements(&achievements)?;
+            report.achievements = true;
+        }
+        if replace || storage.histories()?.is_empty() {
+            storage.insert_histories(&self.history.iter().collect::<Vec<_>>())?;
+            report.history = !self.history.is_empty();
+        }
+        match &self.save {
+            Some(save) if replace || storage.save()?.is_none() => {
+                storage.insert_save(save)?;
+                report.save = true;
+            }
+            None if replace => storage.remove_save()?,
+            _ => {}
+        }
+        if let Some(replay) = &self.replay {
+            if replace || storage.replay()?.is_none() {
+                storage.insert_replay(replay)?;
+                report.replay = true;
+            }
+        }
+        for (key, value) in &self.meta {
+            if replace || storage.meta(key)?.is_none() {
+                storage.insert_meta(key, value)?;
+            }
+        }
+        storage.flush()?;
+
+        Ok(report)
+    }
+
+    /// 检查导出的配置文件，在导入任何数据之前调用，避免只导入一半
+    pub fn check_settings(&self) -> Result<()> {
+        if let Some(settings) = &self.settings {
+            Config::parse(settings).context(i18n::messages().invalid_imported_config)?;
+        }
+        Ok(())
+    }
+
+    /// 写入配置文件。`replace` 为假时只在本地没有配置文件时写入
+    pub fn import_settings(&self, replace: bool) -> Result<bool> {
+        let (settings, path) = match (&self.settings, config::get().settings_path()) {
+            (Some(settings), Some(path)) => (settings, path),
+            _ => return Ok(false),
+        };
+        if path.exists() && !replace {
+            return Ok(false);
+        }
+        self.check_settings()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, settings)
+            .with_context(|| format!("{}{}", i18n::messages().write_failed, path.display()))?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::global::PLAYER_NAME;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn test_import() {
+        let game = Game::with_seed(4, 1);
+        let local = MemoryStorage::default();
+        local.insert_top_score(4, 1000).unwrap();
+        local.insert_top_score(5, 100).unwrap();
+        local.insert_save(&Replay::from_game(&game)).unwrap();
+        let mut stats = Stats::default();
+        stats.record(&game);
+        local.insert_stats(4, &stats).unwrap();
+
+        let remote = MemoryStorage::default();
+        remote.insert_top_score(4, 500).unwrap();
+        remote.insert_top_score(5, 200).unwrap();
+        remote
+            .insert_save(&Replay::from_game(&Game::with_seed(4, 2)))
+            .unwrap();
+        remote.insert_replay(&Replay::from_game(&game)).unwrap();
+        remote.insert_meta(PLAYER_NAME, "bob").unwrap();
+        stats.record(&game);
+        remote.insert_stats(4, &stats).unwrap();
+
+        let json = serde_json::to_string(&Archive::from_storage(&remote).unwrap()).unwrap();
+        let archive: Archive = serde_json::from_str(&json).unwrap();
+
+        let report = archive.import_into(&local, false).unwrap();
+        assert_eq!(vec![5], report.top_scores);
+        assert!(!report.save && report.replay);
+        assert_eq!(1000, local.top_score(4).unwrap());
+        assert_eq!(200, local.top_score(5).unwrap());
+        assert_eq!(1, local.save().unwrap().unwrap().seed);
+        assert_eq!(vec![4], report.stats);
+        assert_eq!(3, local.stats(4).unwrap().games);
+        assert_eq!(Some("bob".to_string()), local.meta(PLAYER_NAME).unwrap());
+
+        archive.import_into(&local, true).unwrap();
+        assert_eq!(500, local.top_score(4).unwrap());
+        assert_eq!(2, local.save().unwrap().unwrap().seed);
+        assert_eq!(2, local.stats(4).unwrap().games);
+    }
+
+    #[test]
+    fn test_check_settings() {
+        let mut archive = Archive::default();
+        assert!(archive.check_settings().is_ok());
+        archive.settings = Some("[ui]\ntheme = \"mono\"".to_string());
+        assert!(archive.check_settings().is_ok());
+        archive.settings = Some("[ui]\ntheme = \"plaid\"".to_string());
+        assert!(archive.check_settings().is_err());
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::archive::Archive;
+use crate::config::{self, AnimationSpeed, Config, Graphics, Theme, PALACE_SIZES};
+use crate::i18n::{self, Messages};
+use crate::palace::{Game, MoveDirection};
+use crate::profile;
+use crate::replay::Replay;
+use crate::stats::Summary;
+use crate::storage::StorageKind;
+use crate::store::Store;
+use crate::ui;
+use crate::util;
+
+/// 一款可以在终端玩的 2048 小游戏
+#[derive(Debug, Parser)]
+#[clap(version)]
+pub struct Cli {
+    /// 数据目录
+    #[clap(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// 存储方式：sled | json | memory
+    #[clap(long, global = true)]
+    pub storage: Option<StorageKind>,
+
+    /// 使用的档案，默认为上次使用的档案；guest 为不保存数据的访客
+    #[clap(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(flatten)]
+    pub play: PlayArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 开始游戏（默认）
+    Play(PlayArgs),
+    /// 查看各模式的最高分和统计
+    Stats {
+        /// 以 JSON 格式输出
+        #[clap(long)]
+        json: bool,
+    },
+    /// 列出全部档案，* 为当前档案
+    Profiles,
+    /// 查看排行榜
+    Leaderboard {
+        /// 宫格大小，默认为全部模式
+        #[clap(long)]
+        size: Option<usize>,
+        /// 以 JSON 格式输出
+        #[clap(long)]
+        json: bool,
+    },
+    /// 在终端中回放一局游戏
+    Replay {
+        /// 回放文件，默认为最近一局
+        file: Option<PathBuf>,
+        /// 每一步之间的间隔（毫秒）
+        #[clap(long, default_value = "300")]
+        delay: u64,
+    },
+    /// 校验回放文件的得分
+    Verify {
+        /// 回放文件
+        file: PathBuf,
+    },
+    /// 自动随机移动，测试游戏引擎的速度
+    Bench {
+        /// 局数
+        #[clap(long, default_value = "100")]
+        games: usize,
+        /// 宫格大小
+        #[clap(long, default_value = "4")]
+        size: usize,
+        /// 随机种子
+        #[clap(long)]
+        seed: Option<u64>,
+    },
+    /// 导出全部数据（最高分、存档、回放、配置）为 JSON
+    Export {
+        /// 输出文件，默认输出到标准输出
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// 导入 export 导出的数据，默认与本地数据合并，最高分取较大值
+    Import {
+        /// 导出的文件
+        file: PathBuf,
+        /// 覆盖本地数据而不是合并
+        #[clap(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Debug, Default, Args)]
+pub struct PlayArgs {
+    /// 宫格大小
+    #[clap(long)]
+    pub size: Option<usize>,
+    /// 第一局的随机种子
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// 主题：random | classic | high-contrast | colorblind | mono
+    #[clap(long)]
+    pub theme: Option<Theme>,
+    /// 动画速度：off | slow | normal | fast
+    #[clap(long)]
+    pub animation: Option<AnimationSpeed>,
+    /// 格子够大时是否用大号数字：true | false
+    #[clap(long)]
+    pub big_digits: Option<bool>,
+    /// 用图片显示宫格：auto | kitty | sixel | off
+    #[clap(long)]
+    pub graphics: Option<Graphics>,
+    /// 逐行输出文字而不是全屏界面，方便读屏软件朗读
+    #[clap(long)]
+    pub line: bool,
+}
+
+/// 解析命令行参数，说明文字使用当前语言
+pub fn parse() -> Cli {
+    let matches = command(i18n::messages()).get_matches();
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+/// 换上指定语言说明文字的命令行界面
+pub fn command(messages: &'static Messages) -> clap::Command<'static> {
+    localize(Cli::command(), "", messages)
+}
+
+/// 替换命令、参数和子命令的说明，`name` 为子命令名，顶层为空
+fn localize(
+    mut command: clap::Command<'static>,
+    name: &str,
+    messages: &'static Messages,
+) -> clap::Command<'static> {
+    if let Some(about) = messages.cli_help(name, "") {
+        command = command.about(about);
+    }
+    let args = command
+        .get_arguments()
+        .map(|a| a.get_id())
+        .collect::<Vec<_>>();
+    for id in args {
+        if let Some(help) = messages.cli_help(name, id) {
+            command = command.mut_arg(id, |arg| arg.help(help));
+        }
+    }
+    let subcommands = command
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .collect::<Vec<_>>();
+    for sub in subcommands {
+        command = command.mut_subcommand(&*sub, |c| localize(c, &sub, messages));
+    }
+    command
+}
+
+impl PlayArgs {
+    /// 命令行参数覆盖配置文件
+    pub fn apply(&self, config: &mut Config) -> Result<()> {
+        if let Some(size) = self.size {
+            config
+                .set_size(size)
+                .with_context(|| format!("{}--size", i18n::messages().invalid_option))?;
+        }
+        if let Some(theme) = self.theme {
+            config.theme = theme;
+        }
+        if let Some(animation) = self.animation {
+            config.animation = animation;
+        }
+        if let Some(big_digits) = self.big_digits {
+            config.big_digits = big_digits;
+        }
+        if let Some(graphics) = self.graphics {
+            config.graphics = graphics;
+        }
+        if self.line {
+            config.line_mode = true;
+        }
+        Ok(())
+    }
+}
+
+/// 执行不需要界面的子命令
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Play(_) => unreachable!("play 需要终端界面"),
+        Command::Stats { json } => stats(json),
+        Command::Profiles => profiles(),
+        Command::Leaderboard { size, json } => leaderboard(size, json),
+        Command::Replay { file, delay } => replay(file, delay),
+        Command::Verify { file } => verify(file),
+        Command::Bench { games, size, seed } => bench(games, size, seed),
+        Command::Export { output } => export(output),
+        Command::Import { file, replace } => import(file, replace),
+    }
+}
+
+fn stats(json: bool) -> Result<()> {
+    let summaries = PALACE_SIZES
+        .iter()
+        .map(|&size| {
+            Ok(Summary::new(
+                size,
+                Store::top_score_of(size)?,
+                &Store::stats_of(size)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+        return Ok(());
+    }
+
+    let messages = i18n::messages();
+    for s in summaries {
+        println!(
+            "{} {} * {}  {} {}",
+            messages.model, s.size, s.size, messages.top_score, s.top_score
+        );
+        if s.games == 0 {
+            continue;
+        }
+        let tiles = s
+            .max_tiles
+            .iter()
+            .map(|(tile, count)| format!("{}×{}", tile, count))
+            .collect::<Vec<_>>();
+        for (name, value) in &[
+            (messages.games, s.games.to_string()),
+            (messages.wins, s.wins.to_string()),
+            (messages.win_rate, format!("{:.1}%", s.win_rate)),
+            (messages.average_score, s.average_score.to_string()),
+            (messages.median_score, s.median_score.to_string()),
+            (messages.longest_game, s.longest_game.to_string()),
+            (messages.total_moves, s.total_moves.to_string()),
+            (messages.highest_tile, s.highest_tile.to_string()),
+            (messages.max_tiles, tiles.join(" ")),
+        ] {
+            println!("    {}  {}", name, value);
+        }
+    }
+    Ok(())
+}
+
+fn profiles() -> Result<()> {
+    let config = config::get();
+    for name in profile::list(&config.data_dir) {
+        let mark = if name == config.profile { "*" } else { " " };
+        println!("{} {}", mark, name);
+    }
+    Ok(())
+}
+
+fn leaderboard(size: Option<usize>, json: bool) -> Result<()> {
+    let sizes = match size {
+        Some(size) => {
+            config::check_size(size)?;
+            vec![size]
+        }
+        None => PALACE_SIZES.to_vec(),
+    };
+    let leaderboards = sizes
+        .into_iter()
+        .map(|size| Ok((size, Store::leaderboard_of(size)?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&leaderboards)?);
+        return Ok(());
+    }
+
+    let messages = i18n::messages();
+    for (size, entries) in leaderboards {
+        println!("{} {} * {}", messages.model, size, size);
+        for (i, e) in entries.iter().enumerate() {
+            println!(
+                "{:>4}. {:<16} {:>8}  {} {}  {} {}  {}{}",
+                i + 1,
+                e.name,
+                e.score,
+                messages.highest_tile,
+                e.max_tile,
+                messages.moves,
+                e.moves,
+                util::date(e.date),
+                if e.undo_used { "  ↺" } else { "" }
+            );
+        }
+    }
+    Ok(())
+}
+
+fn replay(file: Option<PathBuf>, delay: u64) -> Result<()> {
+    let replay = match file {
+        Some(file) => Replay::load(&file)?,
+        None => Store::replay()?.ok_or_else(|| anyhow!(i18n::messages().no_replay))?,
+    };
+    let messages = i18n::messages();
+
+    let game = Game::with_seed(replay.size, replay.seed);
+    println!("{}\n", ui::board_text(&game.palaces));
+    replay.play(|i, step, game| {
+        thread::sleep(Duration::from_millis(delay));
+        println!(
+            "#{} {:?}  {} {}",
+            i + 1,
+            step,
+            messages.score,
+            game.total_score
+        );
+        println!("{}\n", ui::board_text(&game.palaces));
+    });
+    Ok(())
+}
+
+fn verify(file: PathBuf) -> Result<()> {
+    let replay = Replay::load(&file)?;
+    replay
+        .verify()
+        .with_context(|| format!("{}{}", i18n::messages().verify_failed, file.display()))?;
+    println!(
+        "{}: OK ({} {})",
+        file.display(),
+        i18n::messages().score,
+        replay.score
+    );
+    Ok(())
+}
+
+fn bench(games: usize, size: usize, seed: Option<u64>) -> Result<()> {
+    config::check_size(size)?;
+    let directions = [
+        MoveDirection::Up,
+        MoveDirection::Down,
+        MoveDirection::Left,
+        MoveDirection::Right,
+    ];
+    let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+
+    let start = Instant::now();
+    let (mut moves, mut total_score, mut best_score, mut max_tile) = (0_usize, 0_u128, 0_u128, 0);
+    for _ in 0..games {
+        let mut game = Game::with_seed(size, rng.gen());
+        while !game.game_over() {
+            if game
+                .move_palaces(directions[rng.gen_range(0..directions.len())])
+                .changed()
+            {
+                moves += 1;
+            }
+        }
+        total_score += game.total_score;
+        best_score = best_score.max(game.total_score);
+        max_tile = game
+            .palaces
+            .iter()
+            .flatten()
+            .fold(max_tile, |m, &n| m.max(n));
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let messages = i18n::messages();
+    for (name, value) in &[
+        (messages.games, games.to_string()),
+        (messages.moves, moves.to_string()),
+        (messages.duration, format!("{:.3}s", elapsed)),
+        (
+            messages.moves_per_second,
+            format!("{:.0}", moves as f64 / elapsed),
+        ),
+        (
+            messages.average_score,
+            (total_score / games.max(1) as u128).to_string(),
+        ),
+        (messages.top_score, best_score.to_string()),
+        (messages.highest_tile, max_tile.to_string()),
+    ] {
+        println!("{}  {}", name, value);
+    }
+    Ok(())
+}
+
+fn export(output: Option<PathBuf>) -> Result<()> {
+    let json = serde_json::to_string_pretty(&Store::export()?)?;
+    match output {
+        Some(output) => fs::write(&output, json)
+            .with_context(|| format!("{}{}", i18n::messages().write_failed, output.display()))?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn import(file: PathBuf, replace: bool) -> Result<()> {
+    let archive = Archive::load(&file)?;
+    archive.check_settings()?;
+    let report = Store::import(&archive, replace)?;
+    let settings = archive.import_settings(replace)?;
+
+    let messages = i18n::messages();
+    for size in report.top_scores {
+        println!(
+            "{} {} * {}  {} {}",
+            messages.model,
+            size,
+            size,
+            messages.top_score,
+            Store::top_score_of(size)?
+        );
+    }
+    for size in report.stats {
+        println!(
+            "{} {} * {}  {} {}",
+            messages.model,
+            size,
+            size,
+            messages.games,
+            Store::stats_of(size)?.games
+        );
+    }
+    for size in report.leaderboards {
+        println!(
+            "{} {} * {}  {} {}",
+            messages.model,
+            size,
+            size,
+            messages.leaderboard,
+            Store::leaderboard_of(size)?.len()
+        );
+    }
+    let imported = [
+        (report.achievements, messages.achievements),
+        (report.history, messages.history),
+        (report.save, messages.saved_game),
+        (report.replay, messages.replay),
+        (settings, messages.settings),
+    ];
+    for (_, name) in imported.iter().filter(|(imported, _)| *imported) {
+        println!("{} {}", messages.imported, name);
+    }
+    if Store::is_locked() {
+        eprintln!("{}", messages.storage_unsaved);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_help() {
+        let mut command = command(&i18n::EN);
+        let mut help = Vec::new();
+        command.write_help(&mut help).unwrap();
+        let help = String::from_utf8(help).unwrap();
+        assert!(help.contains("A 2048 game to play in the terminal"));
+        assert!(help.contains("Data directory"));
+        assert!(help.is_ascii(), "{}", help);
+
+        for sub in command.get_subcommands_mut() {
+            let mut help = Vec::new();
+            sub.write_help(&mut help).unwrap();
+            let help = String::from_utf8(help).unwrap();
+            assert!(help.is_ascii(), "{}", help);
+        }
+    }
+}
@@ -0,0 +1,787 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::Deserialize;
+
+use crate::i18n;
+use crate::profile::{self, DEFAULT_PROFILE};
+use crate::storage::StorageKind;
+
+/// 配置文件所在目录名
+pub const APP_NAME: &str = "t-2048";
+
+/// 配置文件名
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// 指定数据目录的环境变量
+pub const DATA_DIR_ENV: &str = "T2048_DATA_DIR";
+
+/// 可选的宫格大小
+pub const PALACE_SIZES: [usize; 4] = [3, 4, 5, 6];
+
+/// 切换档案时会替换全局配置，旧的配置在最后一个使用者放下后释放
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// 设为全局配置
+pub fn init(config: Config) -> Arc<Config> {
+    let config = Arc::new(config);
+    *CONFIG.write().unwrap() = Some(config.clone());
+    config
+}
+
+/// 全局配置，未初始化时使用默认配置
+pub fn get() -> Arc<Config> {
+    if let Some(config) = CONFIG.read().unwrap().as_ref() {
+        return config.clone();
+    }
+    CONFIG
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Config::default()))
+        .clone()
+}
+
+/// 默认数据目录 `$XDG_DATA_HOME/t-2048`
+pub fn default_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_NAME))
+        .unwrap_or_else(|| PathBuf::from(".T2048"))
+}
+
+/// 按键对应的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Restart,
+    Undo,
+    Hint,
+    NextModel,
+    PreviousModel,
+    Stats,
+    Leaderboard,
+    Profiles,
+    Achievements,
+    Help,
+    Pause,
+    Quit,
+}
+
+/// 主题
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// 每个数字随机分配颜色
+    Random,
+    /// 按数字大小固定颜色
+    Classic,
+    /// 深浅交替的高对比度颜色
+    HighContrast,
+    /// 色盲也能区分的颜色
+    Colorblind,
+    /// 不用颜色，以粗体、反色和底纹区分数字
+    Mono,
+}
+
+impl Theme {
+    /// 设置了 `NO_COLOR` 或终端不支持颜色时默认为黑白
+    pub fn from_env() -> Self {
+        let no_color = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+        let term = std::env::var("TERM").unwrap_or_default();
+        if no_color || Self::colorless(&term) {
+            Theme::Mono
+        } else {
+            Theme::Random
+        }
+    }
+
+    /// 不支持颜色的终端
+    fn colorless(term: &str) -> bool {
+        ["dumb", "vt100", "vt102", "vt220"].contains(&term)
+    }
+}
+
+/// 动画速度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnimationSpeed {
+    /// 不显示动画
+    Off,
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl AnimationSpeed {
+    /// 一次移动的动画时长，关闭时为空
+    pub fn duration(self) -> Option<Duration> {
+        match self {
+            AnimationSpeed::Off => None,
+            AnimationSpeed::Slow => Some(Duration::from_millis(300)),
+            AnimationSpeed::Normal => Some(Duration::from_millis(180)),
+            AnimationSpeed::Fast => Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+/// 用图片显示宫格的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Graphics {
+    /// 根据终端自动选择，不支持时用文字
+    Auto,
+    /// kitty 图形协议
+    Kitty,
+    Sixel,
+    /// 只用文字
+    Off,
+}
+
+/// 语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Language {
+    #[serde(rename = "zh")]
+    Zh,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Language {
+    /// 根据 `LANG` 环境变量选择语言
+    pub fn from_env() -> Self {
+        std::env::var("LANG")
+            .ok()
+            .and_then(|lang| Self::from_locale(&lang))
+            .unwrap_or(Language::Zh)
+    }
+
+    /// 解析 locale，如 `en_US.UTF-8`
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let locale = locale.to_lowercase();
+        if locale.starts_with("zh") {
+            Some(Language::Zh)
+        } else if locale.starts_with("en") {
+            Some(Language::En)
+        } else {
+            None
+        }
+    }
+}
+
+/// 配置
+#[derive(Debug)]
+pub struct Config {
+    /// 按键绑定
+    pub keys: KeyBindings,
+    /// 默认宫格大小
+    pub size: usize,
+    /// 可撤回的步数，0 表示禁止撤回
+    pub undo_limit: usize,
+    /// 主题
+    pub theme: Theme,
+    /// 动画速度
+    pub animation: AnimationSpeed,
+    /// 格子够大时用大号数字
+    pub big_digits: bool,
+    /// 用图片显示宫格的方式
+    pub graphics: Graphics,
+    /// 不用全屏界面，逐行输出文字，方便读屏软件朗读
+    pub line_mode: bool,
+    /// 重新开始、切换模式和退出前先确认
+    pub confirm: bool,
+    /// 语言
+    pub language: Language,
+    /// 数据目录
+    pub data_dir: PathBuf,
+    /// 存储方式
+    pub storage: StorageKind,
+    /// 当前档案
+    pub profile: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            keys: KeyBindings::default(),
+            size: 4,
+            undo_limit: 1,
+            theme: Theme::from_env(),
+            animation: AnimationSpeed::Normal,
+            big_digits: true,
+            graphics: Graphics::Auto,
+            line_mode: false,
+            confirm: true,
+            language: Language::from_env(),
+            data_dir: default_data_dir(),
+            storage: StorageKind::Sled,
+            profile: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+/// 配置文件内容，未填写的项使用默认值
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    keys: HashMap<String, Vec<String>>,
+    game: GameSection,
+    ui: UiSection,
+    data_dir: Option<PathBuf>,
+    storage: Option<StorageKind>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GameSection {
+    size: Option<usize>,
+    undo_limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UiSection {
+    theme: Option<Theme>,
+    animation: Option<AnimationSpeed>,
+    big_digits: Option<bool>,
+    graphics: Option<Graphics>,
+    line_mode: Option<bool>,
+    confirm: Option<bool>,
+    language: Option<Language>,
+}
+
+impl Config {
+    /// 配置文件路径
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_NAME).join(CONFIG_FILE))
+    }
+
+    /// 读取配置文件，文件不存在时使用默认配置；环境变量中的数据目录优先于配置文件
+    pub fn load() -> Result<Self> {
+        let mut config = match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path)?,
+            _ => Self::default(),
+        };
+        if let Some(data_dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            config.data_dir = PathBuf::from(data_dir);
+        }
+        Ok(config)
+    }
+
+    /// 从指定文件读取配置
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| {
+            format!("{}{}", i18n::messages().read_config_failed, path.display())
+        })?;
+        Self::parse(&content)
+            .with_context(|| format!("{}{}", i18n::messages().invalid_config, path.display()))
+    }
+
+    /// 解析配置内容
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut config = Self::default();
+        config.merge(content)?;
+        Ok(config)
+    }
+
+    /// 切换到指定档案，档案自己的配置文件覆盖全局配置
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        profile::validate(name)?;
+        if let Some(path) = profile::config_path(&self.data_dir, name).filter(|p| p.exists()) {
+            let content = fs::read_to_string(&path).with_context(|| {
+                format!("{}{}", i18n::messages().read_config_failed, path.display())
+            })?;
+            // 数据目录和存储方式只能在全局配置中设置
+            let (data_dir, storage) = (self.data_dir.clone(), self.storage);
+            self.merge(&content).with_context(|| {
+                format!("{}{}", i18n::messages().invalid_config, path.display())
+            })?;
+            self.data_dir = data_dir;
+            self.storage = storage;
+        }
+        self.profile = name.to_string();
+        Ok(())
+    }
+
+    /// 当前档案使用的配置文件，访客档案没有配置文件
+    pub fn settings_path(&self) -> Option<PathBuf> {
+        match self.profile.as_str() {
+            DEFAULT_PROFILE => Self::path(),
+            name => profile::config_path(&self.data_dir, name),
+        }
+    }
+
+    /// 在当前配置上应用配置文件的内容
+    fn merge(&mut self, content: &str) -> Result<()> {
+        let file: ConfigFile = toml::from_str(content)?;
+        let config = self;
+
+        let mut bindings = Vec::new();
+        for (action, keys) in file.keys {
+            let action = Action::from_name(&action)?;
+            let keys = keys
+                .iter()
+                .map(|key| {
+                    parse_key(key).with_context(|| {
+                        format!(
+                            "{}`{}`",
+                            i18n::messages().invalid_key_binding,
+                            action.name()
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            bindings.push((action, keys));
+        }
+        config.keys.bind(bindings)?;
+
+        if let Some(size) = file.game.size {
+            config
+                .set_size(size)
+                .with_context(|| format!("{}game.size", i18n::messages().invalid_option))?;
+        }
+        if let Some(undo_limit) = file.game.undo_limit {
+            config.undo_limit = undo_limit;
+        }
+        if let Some(theme) = file.ui.theme {
+            config.theme = theme;
+        }
+        if let Some(animation) = file.ui.animation {
+            config.animation = animation;
+        }
+        if let Some(big_digits) = file.ui.big_digits {
+            config.big_digits = big_digits;
+        }
+        if let Some(graphics) = file.ui.graphics {
+            config.graphics = graphics;
+        }
+        if let Some(line_mode) = file.ui.line_mode {
+            config.line_mode = line_mode;
+        }
+        if let Some(confirm) = file.ui.confirm {
+            config.confirm = confirm;
+        }
+        if let Some(language) = file.ui.language {
+            config.language = language;
+        }
+        if let Some(data_dir) = file.data_dir {
+            if data_dir.as_os_str().is_empty() {
+                bail!(i18n::messages().empty_data_dir);
+            }
+            config.data_dir = data_dir;
+        }
+        if let Some(storage) = file.storage {
+            config.storage = storage;
+        }
+
+        Ok(())
+    }
+
+    /// 设置默认宫格大小
+    pub fn set_size(&mut self, size: usize) -> Result<()> {
+        check_size(size)?;
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "random" => Ok(Theme::Random),
+            "classic" => Ok(Theme::Classic),
+            "high-contrast" => Ok(Theme::HighContrast),
+            "colorblind" => Ok(Theme::Colorblind),
+            "mono" => Ok(Theme::Mono),
+            _ => bail!(unknown(
+                i18n::messages().unknown_theme,
+                s,
+                "random, classic, high-contrast, colorblind, mono"
+            )),
+        }
+    }
+}
+
+impl FromStr for AnimationSpeed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(AnimationSpeed::Off),
+            "slow" => Ok(AnimationSpeed::Slow),
+            "normal" => Ok(AnimationSpeed::Normal),
+            "fast" => Ok(AnimationSpeed::Fast),
+            _ => bail!(unknown(
+                i18n::messages().unknown_animation,
+                s,
+                "off, slow, normal, fast"
+            )),
+        }
+    }
+}
+
+impl FromStr for Graphics {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(Graphics::Auto),
+            "kitty" => Ok(Graphics::Kitty),
+            "sixel" => Ok(Graphics::Sixel),
+            "off" => Ok(Graphics::Off),
+            _ => bail!(unknown(
+                i18n::messages().unknown_graphics,
+                s,
+                "auto, kitty, sixel, off"
+            )),
+        }
+    }
+}
+
+impl Action {
+    const ALL: [Action; 16] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Restart,
+        Action::Undo,
+        Action::Hint,
+        Action::NextModel,
+        Action::PreviousModel,
+        Action::Stats,
+        Action::Leaderboard,
+        Action::Profiles,
+        Action::Achievements,
+        Action::Help,
+        Action::Pause,
+        Action::Quit,
+    ];
+
+    /// 配置文件中的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Restart => "restart",
+            Action::Undo => "undo",
+            Action::Hint => "hint",
+            Action::NextModel => "next_mode",
+            Action::PreviousModel => "previous_mode",
+            Action::Stats => "stats",
+            Action::Leaderboard => "leaderboard",
+            Action::Profiles => "profiles",
+            Action::Achievements => "achievements",
+            Action::Help => "help",
+            Action::Pause => "pause",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(|a| a.name()).collect::<Vec<_>>();
+                anyhow!(unknown(
+                    i18n::messages().unknown_action,
+                    name,
+                    &names.join(", ")
+                ))
+            })
+    }
+}
+
+/// 按键绑定
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::*;
+        Self {
+            bindings: vec![
+                (Action::Up, vec![Up, Char('k'), Char('w')]),
+                (Action::Down, vec![Down, Char('j'), Char('s')]),
+                (Action::Left, vec![Left, Char('h'), Char('a')]),
+                (Action::Right, vec![Right, Char('l'), Char('d')]),
+                (Action::Restart, vec![Char('r')]),
+                (Action::Undo, vec![Char('z')]),
+                (Action::Hint, vec![Char('i')]),
+                (Action::NextModel, vec![Char('m')]),
+                (Action::PreviousModel, vec![Char('M')]),
+                (Action::Stats, vec![Char('t')]),
+                (Action::Leaderboard, vec![Char('b')]),
+                (Action::Profiles, vec![Char('u')]),
+                (Action::Achievements, vec![Char('c')]),
+                (Action::Help, vec![Char('?')]),
+                (Action::Pause, vec![Char('p')]),
+                (Action::Quit, vec![Char('q'), Esc]),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// 按键对应的操作
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&code))
+            .map(|(action, _)| *action)
+    }
+
+    /// 操作绑定的按键
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// 用配置文件中的绑定替换对应操作原来的按键，其它操作让出被占用的按键；
+    /// 配置的每个操作至少绑定一个按键，替换之后同一个按键不能绑定多个操作
+    fn bind(&mut self, bindings: Vec<(Action, Vec<KeyCode>)>) -> Result<()> {
+        for (action, keys) in &bindings {
+            if keys.is_empty() {
+                bail!("{}`{}`", i18n::messages().unbound_action, action.name());
+            }
+        }
+        for (action, keys) in &mut self.bindings {
+            match bindings.iter().find(|(a, _)| a == action) {
+                Some((_, new_keys)) => *keys = new_keys.clone(),
+                None => {
+                    keys.retain(|key| !bindings.iter().any(|(_, new_keys)| new_keys.contains(key)))
+                }
+            }
+        }
+        self.validate()
+    }
+
+    /// 同一个按键不能绑定多个操作
+    fn validate(&self) -> Result<()> {
+        let mut bound: HashMap<KeyCode, Action> = HashMap::new();
+        for (action, keys) in &self.bindings {
+            for key in keys {
+                if let Some(other) = bound.insert(*key, *action) {
+                    if other != *action {
+                        bail!(
+                            "{}`{}` (`{}`, `{}`)",
+                            i18n::messages().key_conflict,
+                            key_name(*key),
+                            other.name(),
+                            action.name()
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 宫格大小是否受支持
+pub fn check_size(size: usize) -> Result<()> {
+    if !PALACE_SIZES.contains(&size) {
+        let messages = i18n::messages();
+        bail!(
+            "{}{}{}{:?}",
+            messages.unsupported_size,
+            size,
+            messages.choices,
+            PALACE_SIZES
+        );
+    }
+    Ok(())
+}
+
+/// 未知取值的错误信息，附上可选值
+fn unknown(message: &str, value: &str, choices: &str) -> String {
+    format!(
+        "{}`{}`{}{}",
+        message,
+        value,
+        i18n::messages().choices,
+        choices
+    )
+}
+
+/// 解析按键名称，如 `Up`、`Esc`、`F1`、`w`
+pub fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+
+    let code = match name.to_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        lower => match lower.strip_prefix('f').map(str::parse::<u8>) {
+            Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+            _ => bail!("{}`{}`", i18n::messages().unknown_key, name),
+        },
+    };
+    Ok(code)
+}
+
+/// 按键的显示名称
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// 是否为 Ctrl-C：原始模式下 Ctrl-C 不会产生 SIGINT，当作退出处理，同样需要确认
+pub fn interrupt(code: KeyCode, modifiers: KeyModifiers) -> bool {
+    code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r#"
+            data_dir = "/tmp/t-2048"
+            storage = "json"
+
+            [keys]
+            up = ["w", "Up"]
+            quit = ["Esc"]
+
+            [game]
+            size = 5
+            undo_limit = 3
+
+            [ui]
+            theme = "classic"
+            animation = "fast"
+            big_digits = false
+            graphics = "sixel"
+            line_mode = true
+            confirm = false
+            language = "en"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(Action::Up), config.keys.action(KeyCode::Char('w')));
+        assert_eq!(None, config.keys.action(KeyCode::Char('q')));
+        assert_eq!(Some(Action::Down), config.keys.action(KeyCode::Char('j')));
+        assert_eq!(5, config.size);
+        assert_eq!(3, config.undo_limit);
+        assert_eq!(Theme::Classic, config.theme);
+        assert_eq!(AnimationSpeed::Fast, config.animation);
+        assert!(!config.big_digits);
+        assert_eq!(Graphics::Sixel, config.graphics);
+        assert!(config.line_mode);
+        assert!(!config.confirm);
+        assert_eq!(Language::En, config.language);
+        assert_eq!(PathBuf::from("/tmp/t-2048"), config.data_dir);
+        assert_eq!(StorageKind::Json, config.storage);
+
+        assert!(Config::parse("").is_ok());
+        assert!(Config::parse("[game]\nsize = 7").is_err());
+        assert!(Config::parse("[keys]\njump = [\"x\"]").is_err());
+        assert!(Config::parse("[keys]\nup = [\"Upp\"]").is_err());
+        assert!(Config::parse("[keys]\nundo = [\"k\"]\nhint = [\"k\"]").is_err());
+        assert!(Config::parse("[keys]\nundo = []").is_err());
+        assert!(Config::parse("[ui]\ntheme = \"pink\"").is_err());
+        assert!(Config::parse("colour = 1").is_err());
+    }
+
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(Some(Language::En), Language::from_locale("en_US.UTF-8"));
+        assert_eq!(Some(Language::Zh), Language::from_locale("zh_CN.UTF-8"));
+        assert_eq!(None, Language::from_locale("C"));
+    }
+
+    #[test]
+    fn test_theme() {
+        assert!(Theme::colorless("dumb"));
+        assert!(!Theme::colorless("xterm-256color"));
+        assert!(!Theme::colorless(""));
+        assert_eq!(
+            Theme::HighContrast,
+            Config::parse("[ui]\ntheme = \"high-contrast\"")
+                .unwrap()
+                .theme
+        );
+        assert_eq!(Theme::Mono, "mono".parse().unwrap());
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        // `u` 默认是档案，改给撤回之后档案没有按键
+        let config = Config::parse("[keys]\nundo = [\"u\"]").unwrap();
+        assert_eq!(Some(Action::Undo), config.keys.action(KeyCode::Char('u')));
+        assert_eq!(None, config.keys.action(KeyCode::Char('z')));
+        assert!(config.keys.keys(Action::Profiles).is_empty());
+
+        // 档案换了按键，`u` 空出来给撤回
+        let config = Config::parse("[keys]\nprofiles = [\"P\"]\nundo = [\"u\", \"z\"]").unwrap();
+        assert_eq!(Some(Action::Undo), config.keys.action(KeyCode::Char('u')));
+        assert_eq!(
+            Some(Action::Profiles),
+            config.keys.action(KeyCode::Char('P'))
+        );
+
+        // 只让出被占用的按键，其它默认按键保留
+        let config = Config::parse("[keys]\nhint = [\"k\"]").unwrap();
+        assert_eq!(Some(Action::Hint), config.keys.action(KeyCode::Char('k')));
+        assert_eq!(Some(Action::Up), config.keys.action(KeyCode::Char('w')));
+        assert_eq!(None, config.keys.action(KeyCode::Char('i')));
+    }
+
+    #[test]
+    fn test_interrupt() {
+        assert!(interrupt(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        // 不带 Ctrl 的 c 是查看成就
+        assert!(!interrupt(KeyCode::Char('c'), KeyModifiers::NONE));
+        assert_eq!(
+            Some(Action::Achievements),
+            KeyBindings::default().action(KeyCode::Char('c'))
+        );
+    }
+}
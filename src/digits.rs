@@ -0,0 +1,57 @@
+/// 大号数字的行数
+pub const HEIGHT: u16 = 3;
+
+/// 每个数字宽 3 列，用半格方块拼出 3 * 5 的点阵
+const FONT: [[&str; HEIGHT as usize]; 10] = [
+    ["█▀█", "█ █", "▀▀▀"],
+    ["▀█ ", " █ ", "▀▀▀"],
+    ["▀▀█", "█▀▀", "▀▀▀"],
+    ["▀▀█", " ▀█", "▀▀▀"],
+    ["█ █", "▀▀█", "  ▀"],
+    ["█▀▀", "▀▀█", "▀▀▀"],
+    ["█▀▀", "█▀█", "▀▀▀"],
+    ["▀▀█", "  █", "  ▀"],
+    ["█▀█", "█▀█", "▀▀▀"],
+    ["█▀█", "▀▀█", "▀▀▀"],
+];
+
+/// 数字之间空一列
+const GAP: &str = " ";
+
+/// 数字画成大号时的宽度
+pub fn width(num: u128) -> u16 {
+    let len = num.to_string().len() as u16;
+    len * 3 + (len - 1) * GAP.len() as u16
+}
+
+/// 数字画成大号时的每一行
+pub fn lines(num: u128) -> Vec<String> {
+    let digits = num
+        .to_string()
+        .bytes()
+        .map(|b| &FONT[(b - b'0') as usize])
+        .collect::<Vec<_>>();
+    (0..HEIGHT as usize)
+        .map(|row| {
+            digits
+                .iter()
+                .map(|digit| digit[row])
+                .collect::<Vec<_>>()
+                .join(GAP)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(vec!["▀▀█ █▀▀", "█▀▀ █▀█", "▀▀▀ ▀▀▀"], lines(26));
+        assert_eq!(15, width(2048));
+        for line in lines(1234567890) {
+            assert_eq!(width(1234567890) as usize, line.chars().count());
+        }
+    }
+}
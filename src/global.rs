@@ -1,22 +1,16 @@
 use std::collections::HashMap;
 
+use crate::config::{self, Action};
+use crate::i18n;
 use crate::util;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use tui::style::Color;
 
-/// 游戏说明
-pub const GAME_DESCRIPTION: &str = r#"
-1. 按 m 或 M 键选择模式；
-2. 通过控制
-  方向键上下左右
-  或
-  h(左)j(下)k(上)l(右)
-  移动数字方块；
-3. 重新开始(r)
-   撤回(z)
-   退出(q | Esc)
-"#;
+/// 超过这个时间没有操作时暂停计时。crossterm 0.19 不能报告终端失去焦点，
+/// 切换到别的窗口时只能由此判断
+pub const IDLE_PAUSE: Duration = Duration::from_secs(30);
 
 /// 宫格初始大小 n*n
 pub static mut PALACE_SIZE: usize = 4;
@@ -24,33 +18,129 @@ pub static mut PALACE_SIZE: usize = 4;
 /// 状态键
 pub const HISTORY: &str = "history";
 
+/// 回放键
+pub const REPLAY: &str = "replay";
+
+/// 存档键
+pub const SAVE: &str = "save";
+
+/// 成就键
+pub const ACHIEVEMENTS: &str = "achievements";
+
+/// 已导入旧版本数据的标记
+pub const LEGACY_IMPORTED: &str = "legacy_imported";
+
+/// 上次输入的名字
+pub const PLAYER_NAME: &str = "player_name";
+
+/// 导出时包含的元数据键
+pub const META_KEYS: [&str; 2] = [LEGACY_IMPORTED, PLAYER_NAME];
+
+/// 游戏说明，按键取自当前的按键绑定
+pub fn game_description() -> String {
+    let config = config::get();
+    let messages = i18n::messages();
+    let line = |action: Action| {
+        let keys = config
+            .keys
+            .keys(action)
+            .iter()
+            .map(|&key| config::key_name(key))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        // 默认按键都让给了其它操作时没有按键
+        let keys = if keys.is_empty() {
+            "-".to_string()
+        } else {
+            keys
+        };
+        format!("   {}({})\n", messages.action(action), keys)
+    };
+
+    let mut description = String::from("\n");
+    description.push_str(&format!("1. {}\n", messages.change_model));
+    description.extend(
+        [Action::NextModel, Action::PreviousModel]
+            .iter()
+            .map(|&a| line(a)),
+    );
+    description.push_str(&format!("2. {}\n", messages.move_palaces));
+    description.extend(
+        [Action::Up, Action::Down, Action::Left, Action::Right]
+            .iter()
+            .map(|&a| line(a)),
+    );
+    description.push_str(&format!("3. {}\n", messages.game));
+    description.extend(
+        [
+            Action::Restart,
+            Action::Undo,
+            Action::Hint,
+            Action::Stats,
+            Action::Leaderboard,
+            Action::Profiles,
+            Action::Achievements,
+            Action::Help,
+            Action::Pause,
+            Action::Quit,
+        ]
+        .iter()
+        .map(|&a| line(a)),
+    );
+    description
+}
+
 lazy_static! {
     /// 颜色数组
-    pub static ref NUM_COLOR: Vec<Color> = {
-        let mut num_color = Vec::new();
-        num_color.push(Color::Rgb(127, 156, 138));
-        num_color.push(Color::Rgb(58, 233, 102));
-        num_color.push(Color::Rgb(158, 88, 105));
-        num_color.push(Color::Rgb(228, 101, 39));
-        num_color.push(Color::Rgb(211,84,0));
-        num_color.push(Color::Rgb(233, 121, 58));
-        num_color.push(Color::Rgb(13, 141, 72));
-        num_color.push(Color::Rgb(78, 110, 227));
-        num_color.push(Color::Rgb(175, 132, 247));
-        num_color.push(Color::Rgb(255, 68, 26));
-        num_color.push(Color::Rgb(225, 11, 43));
-        num_color.push(Color::Rgb(232, 139, 0));
-        num_color.push(Color::Rgb(255, 176, 97));
-        num_color.push(Color::Rgb(255, 123, 36));
-        num_color.push(Color::Rgb(2, 179, 64));
-        num_color.push(Color::Rgb(115, 46, 126));
-        num_color
-    };
+    pub static ref NUM_COLOR: Vec<Color> = vec![
+        Color::Rgb(127, 156, 138),
+        Color::Rgb(58, 233, 102),
+        Color::Rgb(158, 88, 105),
+        Color::Rgb(228, 101, 39),
+        Color::Rgb(211, 84, 0),
+        Color::Rgb(233, 121, 58),
+        Color::Rgb(13, 141, 72),
+        Color::Rgb(78, 110, 227),
+        Color::Rgb(175, 132, 247),
+        Color::Rgb(255, 68, 26),
+        Color::Rgb(225, 11, 43),
+        Color::Rgb(232, 139, 0),
+        Color::Rgb(255, 176, 97),
+        Color::Rgb(255, 123, 36),
+        Color::Rgb(2, 179, 64),
+        Color::Rgb(115, 46, 126),
+    ];
+    /// 高对比度主题的颜色，深浅交替
+    pub static ref HIGH_CONTRAST_COLOR: Vec<Color> = vec![
+        Color::Rgb(255, 255, 255),
+        Color::Rgb(0, 0, 170),
+        Color::Rgb(255, 255, 0),
+        Color::Rgb(170, 0, 0),
+        Color::Rgb(0, 255, 255),
+        Color::Rgb(0, 110, 0),
+        Color::Rgb(255, 0, 255),
+        Color::Rgb(90, 0, 140),
+        Color::Rgb(255, 160, 0),
+        Color::Rgb(0, 90, 110),
+        Color::Rgb(160, 255, 0),
+    ];
+    /// 色盲友好主题的颜色，取自 Okabe-Ito 配色
+    pub static ref COLORBLIND_COLOR: Vec<Color> = vec![
+        Color::Rgb(230, 159, 0),
+        Color::Rgb(86, 180, 233),
+        Color::Rgb(0, 158, 115),
+        Color::Rgb(240, 228, 66),
+        Color::Rgb(0, 114, 178),
+        Color::Rgb(213, 94, 0),
+        Color::Rgb(204, 121, 167),
+        Color::Rgb(153, 153, 153),
+    ];
     /// 数字对应的颜色
     pub static ref PALACE_COLOR: Arc<Mutex<HashMap<u128, Color>>> = {
         let mut num_color_map = HashMap::new();
         (0..16).for_each(|i|{
-            num_color_map.insert(2_u128.rotate_left(i), util::color());
+            let num = 2_u128.rotate_left(i);
+            num_color_map.insert(num, util::num_color(num));
         });
         Arc::new(Mutex::new(num_color_map))
     };
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, Write};
+
+use crossterm::{cursor::MoveTo, queue};
+use tui::layout::Rect;
+
+use crate::config::Graphics;
+use crate::global;
+use crate::raster::{self, Image};
+use crate::util;
+
+/// 终端支持的图形协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+impl Protocol {
+    /// 按配置选择协议，`auto` 时根据环境变量判断终端是否支持
+    pub fn detect(graphics: Graphics) -> Option<Self> {
+        match graphics {
+            Graphics::Kitty => Some(Protocol::Kitty),
+            Graphics::Sixel => Some(Protocol::Sixel),
+            Graphics::Off => None,
+            Graphics::Auto => Self::from_env(|key| env::var(key).ok()),
+        }
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        // tmux 和 screen 需要额外转发图形数据，不自动开启
+        if var("TMUX").is_some() || var("STY").is_some() {
+            return None;
+        }
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || var("KITTY_WINDOW_ID").is_some()
+            || ["WezTerm", "ghostty"].contains(&program.as_str())
+        {
+            return Some(Protocol::Kitty);
+        }
+        if term.contains("sixel")
+            || ["foot", "mlterm", "contour", "yaft"]
+                .iter()
+                .any(|name| term.starts_with(name))
+            || ["mintty", "iTerm.app"].contains(&program.as_str())
+        {
+            return Some(Protocol::Sixel);
+        }
+        None
+    }
+}
+
+/// 终端不报告像素大小时假定的字符大小
+const DEFAULT_CELL: (u32, u32) = (10, 20);
+
+/// 一个字符的像素大小
+#[cfg(unix)]
+fn cell_size() -> (u32, u32) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if !ok || size.ws_col == 0 || size.ws_row == 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 {
+        return DEFAULT_CELL;
+    }
+    (
+        (size.ws_xpixel / size.ws_col) as u32,
+        (size.ws_ypixel / size.ws_row) as u32,
+    )
+}
+
+#[cfg(not(unix))]
+fn cell_size() -> (u32, u32) {
+    DEFAULT_CELL
+}
+
+/// 用图片画宫格，只重画和上次不同的格子
+pub struct Renderer {
+    protocol: Protocol,
+    /// 屏幕上的格子和数字
+    shown: Vec<(Rect, u128)>,
+    /// kitty 已传输的图片：数字、宽、高对应的图片 id
+    images: HashMap<(u128, u32, u32), u32>,
+    /// 下一张图片的 id
+    next_id: u32,
+}
+
+impl Renderer {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            shown: Vec::new(),
+            images: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// 屏幕上是否有图片
+    pub fn is_shown(&self) -> bool {
+        !self.shown.is_empty()
+    }
+
+    /// 画出宫格；`screen` 为整个屏幕，sixel 不画到最后一行，以免终端滚动
+    pub fn draw(
+        &mut self,
+        out: &mut impl Write,
+        tiles: &[(Rect, u128)],
+        screen: Rect,
+    ) -> io::Result<()> {
+        // 布局变化时全部重画
+        if self.shown.len() != tiles.len() || self.shown.iter().zip(tiles).any(|(a, b)| a.0 != b.0)
+        {
+            self.clear(out)?;
+        }
+        let (cell_w, cell_h) = cell_size();
+        if self.protocol == Protocol::Kitty {
+            let sizes = tiles
+                .iter()
+                .map(|(rect, _)| (rect.width as u32 * cell_w, rect.height as u32 * cell_h))
+                .collect::<Vec<_>>();
+            self.evict(out, &sizes)?;
+        }
+        for (i, &(rect, num)) in tiles.iter().enumerate() {
+            if self.shown.get(i) == Some(&(rect, num)) {
+                continue;
+            }
+            let rows = match self.protocol {
+                Protocol::Sixel if rect.bottom() >= screen.bottom() => rect.height - 1,
+                _ => rect.height,
+            };
+            if rect.width == 0 || rows == 0 {
+                continue;
+            }
+            let (width, height) = (rect.width as u32 * cell_w, rows as u32 * cell_h);
+            queue!(out, MoveTo(rect.x, rect.y))
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            match self.protocol {
+                Protocol::Kitty => {
+                    let id = match self.images.get(&(num, width, height)) {
+                        Some(&id) => id,
+                        None => {
+                            let id = self.next_id;
+                            self.next_id += 1;
+                            let image = raster::tile(num, color(num), width, height);
+                            out.write_all(kitty_transmit(&image, id).as_bytes())?;
+                            self.images.insert((num, width, height), id);
+                            id
+                        }
+                    };
+                    // 每一格的放置 id 固定，换成另一张图片时先删掉原来的
+                    let placement = i + 1;
+                    let old = self.shown.get(i).map(|&(_, old)| (old, width, height));
+                    if let Some(old_id) = old.and_then(|key| self.images.get(&key)) {
+                        write!(out, "\x1b_Ga=d,d=i,i={},p={},q=2\x1b\\", old_id, placement)?;
+                    }
+                    write!(
+                        out,
+                        "\x1b_Ga=p,i={},p={},c={},r={},C=1,q=2\x1b\\",
+                        id, placement, rect.width, rows
+                    )?;
+                }
+                Protocol::Sixel => {
+                    let image = raster::tile(num, color(num), width, height);
+                    out.write_all(sixel(&image).as_bytes())?;
+                }
+            }
+        }
+        // tui 下一次绘制时假定光标从左上角开始
+        queue!(out, MoveTo(0, 0)).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        out.flush()?;
+        self.shown = tiles.to_vec();
+        Ok(())
+    }
+
+    /// 格子大小变化后，旧大小的图片不会再用到，从终端和缓存中一起删掉
+    fn evict(&mut self, out: &mut impl Write, sizes: &[(u32, u32)]) -> io::Result<()> {
+        let stale = self
+            .images
+            .iter()
+            .filter(|((_, width, height), _)| !sizes.contains(&(*width, *height)))
+            .map(|(&key, &id)| (key, id))
+            .collect::<Vec<_>>();
+        for (key, id) in stale {
+            // 大写的 I 同时释放图片数据，小写只删除放置
+            write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)?;
+            self.images.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// 删除画出的图片。sixel 直接画在屏幕上，需要调用方重画整个屏幕来清除
+    pub fn clear(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.protocol == Protocol::Kitty && self.is_shown() {
+            out.write_all(b"\x1b_Ga=d,d=a,q=2\x1b\\")?;
+            out.flush()?;
+        }
+        self.shown.clear();
+        Ok(())
+    }
+}
+
+/// 数字对应的颜色，与文字界面一致
+fn color(num: u128) -> tui::style::Color {
+    if num == 0 {
+        return tui::style::Color::Rgb(60, 60, 60);
+    }
+    *global::PALACE_COLOR
+        .lock()
+        .unwrap()
+        .entry(num)
+        .or_insert_with(|| util::num_color(num))
+}
+
+/// kitty 一次最多传输的 base64 字节数
+const KITTY_CHUNK: usize = 4096;
+
+/// 以 kitty 图形协议传输 RGBA 图片，之后按 `id` 放置
+fn kitty_transmit(image: &Image, id: u32) -> String {
+    let data = image.pixels.iter().flatten().copied().collect::<Vec<_>>();
+    let encoded = base64(&data);
+    let chunks = encoded.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=t,f=32,s={},v={},i={},q=2,m={};",
+                image.width, image.height, id, more
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};", more));
+        }
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// sixel 最多使用的颜色数
+const SIXEL_COLORS: usize = 255;
+
+/// 编码为 sixel，半透明以下的像素不画，保留终端原来的内容
+fn sixel(image: &Image) -> String {
+    // 每个通道取高 5 位统计颜色，取最常见的作为调色板
+    let key =
+        |p: [u8; 4]| ((p[0] as u32 >> 3) << 10) | ((p[1] as u32 >> 3) << 5) | (p[2] as u32 >> 3);
+    let mut counts = HashMap::new();
+    for &p in image.pixels.iter().filter(|p| p[3] >= 128) {
+        *counts.entry(key(p)).or_insert(0) += 1;
+    }
+    let mut palette = counts.into_iter().collect::<Vec<_>>();
+    palette.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    palette.truncate(SIXEL_COLORS);
+    let palette = palette
+        .into_iter()
+        .map(|(k, _)| [(k >> 10) as u8, (k >> 5 & 31) as u8, (k & 31) as u8])
+        .collect::<Vec<_>>();
+
+    // 每个像素对应的调色板序号，透明为空
+    let mut nearest = HashMap::new();
+    let indexes = image
+        .pixels
+        .iter()
+        .map(|&p| {
+            (p[3] >= 128).then(|| {
+                *nearest.entry(key(p)).or_insert_with(|| {
+                    let c = [p[0] >> 3, p[1] >> 3, p[2] >> 3];
+                    (0..palette.len())
+                        .min_by_key(|&i| {
+                            (0..3)
+                                .map(|j| (palette[i][j] as i32 - c[j] as i32).pow(2))
+                                .sum::<i32>()
+                        })
+                        .unwrap_or_default()
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", image.width, image.height);
+    for (i, c) in palette.iter().enumerate() {
+        let percent = |v: u8| v as u32 * 100 / 31;
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            i,
+            percent(c[0]),
+            percent(c[1]),
+            percent(c[2])
+        ));
+    }
+    let (width, height) = (image.width as usize, image.height as usize);
+    for top in (0..height).step_by(6) {
+        let band = top..(top + 6).min(height);
+        let mut used = band
+            .clone()
+            .flat_map(|y| indexes[y * width..(y + 1) * width].iter().flatten())
+            .copied()
+            .collect::<Vec<_>>();
+        used.sort_unstable();
+        used.dedup();
+        for (n, &color) in used.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", color));
+            let columns = (0..width).map(|x| {
+                band.clone().fold(0u8, |bits, y| {
+                    if indexes[y * width + x] == Some(color) {
+                        bits | 1 << (y - top)
+                    } else {
+                        bits
+                    }
+                })
+            });
+            push_runs(&mut out, columns);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// 连续相同的 sixel 字符用 `!次数` 压缩
+fn push_runs(out: &mut String, columns: impl Iterator<Item = u8>) {
+    let mut run: Option<(u8, usize)> = None;
+    let flush = |out: &mut String, (bits, count): (u8, usize)| {
+        let c = (bits + 63) as char;
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, c));
+        } else {
+            (0..count).for_each(|_| out.push(c));
+        }
+    };
+    for bits in columns {
+        run = match run {
+            Some((b, count)) if b == bits => Some((b, count + 1)),
+            Some(last) => {
+                flush(out, last);
+                Some((bits, 1))
+            }
+            None => Some((bits, 1)),
+        };
+    }
+    if let Some(last) = run {
+        flush(out, last);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            Some(Protocol::Kitty),
+            Protocol::from_env(env(&[("TERM", "xterm-kitty")]))
+        );
+        assert_eq!(
+            Some(Protocol::Sixel),
+            Protocol::from_env(env(&[("TERM", "foot")]))
+        );
+        assert_eq!(None, Protocol::from_env(env(&[("TERM", "xterm-256color")])));
+        assert_eq!(
+            None,
+            Protocol::from_env(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")]))
+        );
+        assert_eq!(Some(Protocol::Sixel), Protocol::detect(Graphics::Sixel));
+        assert_eq!(None, Protocol::detect(Graphics::Off));
+    }
+
+    #[test]
+    fn test_evict() {
+        let mut renderer = Renderer::new(Protocol::Kitty);
+        let screen = Rect::new(0, 0, 80, 24);
+        let mut out = Vec::new();
+        let small = [(Rect::new(0, 0, 4, 2), 2), (Rect::new(4, 0, 4, 2), 4)];
+        renderer.draw(&mut out, &small, screen).unwrap();
+        assert_eq!(2, renderer.images.len());
+
+        let mut out = Vec::new();
+        let large = [(Rect::new(0, 0, 6, 3), 2), (Rect::new(6, 0, 6, 3), 4)];
+        renderer.draw(&mut out, &large, screen).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("a=d,d=I,i=1,"));
+        assert!(out.contains("a=d,d=I,i=2,"));
+        assert_eq!(2, renderer.images.len());
+        assert!(renderer.images.values().all(|&id| id > 2));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9vYmFy", base64(b"foobar"));
+
+        let mut image = Image::new(3, 7);
+        image.pixels[0] = [255, 0, 0, 255];
+        image.pixels[1] = [255, 0, 0, 255];
+        image.pixels[20] = [0, 0, 255, 255];
+        assert_eq!(
+            "\x1bP0;1;0q\"1;1;3;7#0;2;100;0;0#1;2;0;0;100#0@@?-#1??@-\x1b\\",
+            sixel(&image)
+        );
+
+        let transmit = kitty_transmit(&Image::new(40, 40), 3);
+        assert!(transmit.starts_with("\x1b_Ga=t,f=32,s=40,v=40,i=3,q=2,m=1;"));
+        assert_eq!(3, transmit.matches("\x1b\\").count());
+        assert!(transmit.contains("\x1b_Gm=0;"));
+    }
+}
@@ -0,0 +1,549 @@
+use crate::config::{self, Action, Language};
+use crate::palace::MoveDirection;
+
+/// 界面文字
+pub struct Messages {
+    /// 游戏说明标题
+    pub description: &'static str,
+    /// 选择模式
+    pub change_model: &'static str,
+    /// 移动数字方块
+    pub move_palaces: &'static str,
+    /// 游戏操作
+    pub game: &'static str,
+    /// 模式标题
+    pub model: &'static str,
+    /// 得分标题
+    pub score: &'static str,
+    /// 最高分标题
+    pub top_score: &'static str,
+    /// 游戏结束标题
+    pub game_over: &'static str,
+    /// 打破最高分
+    pub best_beaten: &'static str,
+    /// 得分走势标题
+    pub trend: &'static str,
+    /// 上一步的得分
+    pub last_gain: &'static str,
+    /// 终端太小时的提示
+    pub too_small: &'static str,
+    /// 逐行模式的播报
+    pub line_mode: &'static str,
+    pub merged: &'static str,
+    pub nothing_moved: &'static str,
+    pub spawned: &'static str,
+    pub row: &'static str,
+    pub column: &'static str,
+    pub unbound_key: &'static str,
+    pub nothing_to_undo: &'static str,
+    pub profiles_hint: &'static str,
+    /// 播报的标点
+    pub full_stop: &'static str,
+    pub comma: &'static str,
+    /// 操作名称
+    pub up: &'static str,
+    pub down: &'static str,
+    pub left: &'static str,
+    pub right: &'static str,
+    pub restart: &'static str,
+    pub undo: &'static str,
+    pub hint: &'static str,
+    pub next_model: &'static str,
+    pub previous_model: &'static str,
+    pub stats: &'static str,
+    pub leaderboard: &'static str,
+    pub profiles: &'static str,
+    pub achievements: &'static str,
+    pub help: &'static str,
+    pub pause: &'static str,
+    pub quit: &'static str,
+    /// 帮助和暂停
+    pub help_scroll: &'static str,
+    pub mouse: &'static str,
+    pub mouse_help: &'static str,
+    pub paused: &'static str,
+    /// 确认对话框
+    pub confirm: &'static str,
+    pub confirm_restart: &'static str,
+    pub confirm_model: &'static str,
+    pub confirm_quit: &'static str,
+    pub confirm_keys: &'static str,
+    /// 统计项
+    pub games: &'static str,
+    pub wins: &'static str,
+    pub win_rate: &'static str,
+    pub average_score: &'static str,
+    pub median_score: &'static str,
+    pub longest_game: &'static str,
+    pub total_moves: &'static str,
+    pub highest_tile: &'static str,
+    pub max_tiles: &'static str,
+    /// 排行榜各列
+    pub name: &'static str,
+    pub moves: &'static str,
+    pub moves_per_second: &'static str,
+    pub duration: &'static str,
+    pub date: &'static str,
+    pub seed: &'static str,
+    /// 达成成就的提示
+    pub achievement_unlocked: &'static str,
+    /// 各成就的 id、名字和说明
+    pub achievement_names: &'static [(&'static str, &'static str, &'static str)],
+    /// 命令行的说明，键为 `子命令.参数`，子命令本身的说明键为子命令名，程序说明键为空；
+    /// 找不到 `子命令.参数` 时使用 `参数`
+    pub cli_help: &'static [(&'static str, &'static str)],
+    /// 新建档案
+    pub new_profile: &'static str,
+    pub profile_name: &'static str,
+    /// 输入名字的提示
+    pub new_record: &'static str,
+    pub enter_name: &'static str,
+    /// 导入数据
+    pub imported: &'static str,
+    pub history: &'static str,
+    pub saved_game: &'static str,
+    pub replay: &'static str,
+    pub settings: &'static str,
+    /// 导入旧版本数据
+    pub legacy_imported: &'static str,
+    pub legacy_import_failed: &'static str,
+    /// 数据库被另一个实例占用
+    pub storage_locked: &'static str,
+    pub storage_unsaved: &'static str,
+    /// 错误信息，后面接文件路径、选项或取值
+    pub read_failed: &'static str,
+    pub write_failed: &'static str,
+    pub malformed: &'static str,
+    pub upgrade_failed: &'static str,
+    pub open_db_failed: &'static str,
+    pub read_config_failed: &'static str,
+    pub invalid_config: &'static str,
+    pub invalid_imported_config: &'static str,
+    pub invalid_option: &'static str,
+    pub invalid_record: &'static str,
+    pub invalid_key_binding: &'static str,
+    pub unbound_action: &'static str,
+    pub key_conflict: &'static str,
+    pub unknown_key: &'static str,
+    pub unknown_action: &'static str,
+    pub unknown_theme: &'static str,
+    pub unknown_animation: &'static str,
+    pub unknown_graphics: &'static str,
+    pub unknown_storage: &'static str,
+    pub unsupported_size: &'static str,
+    /// 可选值列表的前缀
+    pub choices: &'static str,
+    pub empty_data_dir: &'static str,
+    pub empty_profile_name: &'static str,
+    pub profile_name_too_long: &'static str,
+    pub invalid_profile_name: &'static str,
+    pub unsupported_version: &'static str,
+    pub upgrade_hint: &'static str,
+    pub invalid_version: &'static str,
+    pub migration_failed: &'static str,
+    pub no_replay: &'static str,
+    pub verify_failed: &'static str,
+    pub invalid_undo: &'static str,
+    pub score_mismatch: &'static str,
+    pub recomputed: &'static str,
+    pub board_mismatch: &'static str,
+}
+
+/// 简体中文
+pub const ZH: Messages = Messages {
+    description: "游戏说明",
+    change_model: "选择模式",
+    move_palaces: "移动数字方块",
+    game: "游戏",
+    model: "模式",
+    score: "得分",
+    top_score: "最高分",
+    game_over: "游戏结束",
+    best_beaten: "打破纪录！",
+    trend: "走势",
+    last_gain: "上一步",
+    too_small: "终端太小，至少需要",
+    line_mode: "逐行模式",
+    merged: "合并",
+    nothing_moved: "没有方块移动",
+    spawned: "新数字",
+    row: "行",
+    column: "列",
+    unbound_key: "这个按键没有绑定操作",
+    nothing_to_undo: "没有可以撤回的操作",
+    profiles_hint: "输入档案名切换或新建档案，Esc 留在当前档案",
+    full_stop: "。",
+    comma: "，",
+    up: "上",
+    down: "下",
+    left: "左",
+    right: "右",
+    restart: "重新开始",
+    undo: "撤回",
+    hint: "提示",
+    next_model: "下一个",
+    previous_model: "上一个",
+    stats: "统计",
+    leaderboard: "排行榜",
+    profiles: "档案",
+    achievements: "成就",
+    help: "帮助",
+    pause: "暂停",
+    quit: "退出",
+    help_scroll: "↑↓ 滚动，其它键关闭",
+    mouse: "鼠标",
+    mouse_help: "在宫格上拖动即可移动，点击模式列表切换大小，右下角的按钮可以点击",
+    paused: "已暂停，按任意键继续",
+    confirm: "确认",
+    confirm_restart: "放弃当前这一局，重新开始？",
+    confirm_model: "放弃当前这一局，切换模式？",
+    confirm_quit: "退出游戏？",
+    confirm_keys: "y / Enter 确认，其它键取消",
+    games: "局数",
+    wins: "获胜",
+    win_rate: "胜率",
+    average_score: "平均分",
+    median_score: "中位数",
+    longest_game: "最长一局",
+    total_moves: "总步数",
+    highest_tile: "最大数字",
+    max_tiles: "最大数字分布",
+    name: "名字",
+    moves: "步数",
+    moves_per_second: "每秒步数",
+    duration: "用时",
+    date: "日期",
+    seed: "种子",
+    achievement_unlocked: "达成成就",
+    cli_help: &[
+        ("", "一款可以在终端玩的 2048 小游戏"),
+        ("data-dir", "数据目录"),
+        ("storage", "存储方式：sled | json | memory"),
+        (
+            "profile",
+            "使用的档案，默认为上次使用的档案；guest 为不保存数据的访客",
+        ),
+        ("size", "宫格大小"),
+        ("seed", "第一局的随机种子"),
+        (
+            "theme",
+            "主题：random | classic | high-contrast | colorblind | mono",
+        ),
+        ("animation", "动画速度：off | slow | normal | fast"),
+        ("big-digits", "格子够大时是否用大号数字：true | false"),
+        ("graphics", "用图片显示宫格：auto | kitty | sixel | off"),
+        ("line", "逐行输出文字而不是全屏界面，方便读屏软件朗读"),
+        ("json", "以 JSON 格式输出"),
+        ("play", "开始游戏（默认）"),
+        ("stats", "查看各模式的最高分和统计"),
+        ("profiles", "列出全部档案，* 为当前档案"),
+        ("leaderboard", "查看排行榜"),
+        ("leaderboard.size", "宫格大小，默认为全部模式"),
+        ("replay", "在终端中回放一局游戏"),
+        ("replay.file", "回放文件，默认为最近一局"),
+        ("replay.delay", "每一步之间的间隔（毫秒）"),
+        ("verify", "校验回放文件的得分"),
+        ("verify.file", "回放文件"),
+        ("bench", "自动随机移动，测试游戏引擎的速度"),
+        ("bench.games", "局数"),
+        ("bench.seed", "随机种子"),
+        ("export", "导出全部数据（最高分、存档、回放、配置）为 JSON"),
+        ("export.output", "输出文件，默认输出到标准输出"),
+        (
+            "import",
+            "导入 export 导出的数据，默认与本地数据合并，最高分取较大值",
+        ),
+        ("import.file", "导出的文件"),
+        ("import.replace", "覆盖本地数据而不是合并"),
+    ],
+    achievement_names: &[
+        ("first_game", "初来乍到", "完成第一局"),
+        ("first_2048", "2048!", "第一次合成 2048"),
+        ("tile_4096", "更进一步", "在 4 * 4 模式合成 4096"),
+        ("perfect_3x3", "小而完美", "在 3 * 3 模式不撤回合成 512"),
+        ("quick_score", "速战速决", "250 步以内得到 3000 分"),
+        ("four_merges", "四连合并", "一步合并四对数字"),
+        ("score_20000", "高分玩家", "单局得到 20000 分"),
+        ("games_10", "常客", "完成 10 局"),
+        ("games_100", "老玩家", "完成 100 局"),
+    ],
+    new_profile: "+ 新建档案",
+    profile_name: "档案名",
+    new_record: "进入排行榜",
+    enter_name: "输入名字后按 Enter 保存，Esc 跳过",
+    imported: "已导入",
+    history: "撤回记录",
+    saved_game: "存档",
+    replay: "回放",
+    settings: "配置",
+    legacy_imported: "已导入旧版本的数据：",
+    legacy_import_failed: "导入旧版本的数据失败：",
+    storage_locked:
+        "数据库正被另一个 t-2048 使用，本次的数据暂存在内存中，解锁后会合并最高分和统计",
+    storage_unsaved: "数据库仍被另一个 t-2048 占用，本次的数据没有保存",
+    read_failed: "无法读取 ",
+    write_failed: "无法写入 ",
+    malformed: "文件格式有误：",
+    upgrade_failed: "无法升级 ",
+    open_db_failed: "无法打开数据库 ",
+    read_config_failed: "无法读取配置文件 ",
+    invalid_config: "配置文件有误：",
+    invalid_imported_config: "导入的配置文件有误",
+    invalid_option: "选项有误：",
+    invalid_record: "记录有误：",
+    invalid_key_binding: "按键绑定有误：",
+    unbound_action: "操作没有绑定按键：",
+    key_conflict: "按键同时绑定了多个操作：",
+    unknown_key: "无法识别的按键：",
+    unknown_action: "未知的操作：",
+    unknown_theme: "未知的主题：",
+    unknown_animation: "未知的动画速度：",
+    unknown_graphics: "未知的图形显示方式：",
+    unknown_storage: "未知的存储方式：",
+    unsupported_size: "不支持的宫格大小：",
+    choices: "，可选值为 ",
+    empty_data_dir: "data_dir 不能为空",
+    empty_profile_name: "档案名不能为空",
+    profile_name_too_long: "档案名过长，最多可用的字符数为 ",
+    invalid_profile_name: "档案名不能以 . 开头或包含 / \\：",
+    unsupported_version: "数据版本高于当前支持的版本：",
+    upgrade_hint: "，请升级 t-2048",
+    invalid_version: "数据版本号有误",
+    migration_failed: "数据升级失败，原版本为 ",
+    no_replay: "还没有可回放的游戏",
+    verify_failed: "校验失败：",
+    invalid_undo: "回放中包含无效的撤回操作",
+    score_mismatch: "得分不一致：记录为 ",
+    recomputed: "，重新演算为 ",
+    board_mismatch: "最终宫格与重新演算的结果不一致",
+};
+
+/// English
+pub const EN: Messages = Messages {
+    description: "Help",
+    change_model: "Change mode",
+    move_palaces: "Move the tiles",
+    game: "Game",
+    model: "Mode",
+    score: "Score",
+    top_score: "Best",
+    game_over: "Game Over",
+    best_beaten: "New best!",
+    trend: "Trend",
+    last_gain: "Last move",
+    too_small: "Terminal too small, need at least",
+    line_mode: "Line mode",
+    merged: "Merged",
+    nothing_moved: "Nothing moved",
+    spawned: "New tile",
+    row: "row",
+    column: "column",
+    unbound_key: "That key does nothing",
+    nothing_to_undo: "Nothing to undo",
+    profiles_hint: "Type a profile name to switch to or create it, Esc to stay",
+    full_stop: ". ",
+    comma: ", ",
+    up: "Up",
+    down: "Down",
+    left: "Left",
+    right: "Right",
+    restart: "Restart",
+    undo: "Undo",
+    hint: "Hint",
+    next_model: "Next",
+    previous_model: "Previous",
+    stats: "Stats",
+    leaderboard: "Leaderboard",
+    profiles: "Profiles",
+    achievements: "Achievements",
+    help: "Help",
+    pause: "Pause",
+    quit: "Quit",
+    help_scroll: "↑↓ to scroll, any other key to close",
+    mouse: "Mouse",
+    mouse_help: "Drag on the board to move, click the mode list to change size, click the buttons at the bottom right",
+    paused: "Paused, press any key to continue",
+    confirm: "Confirm",
+    confirm_restart: "Abandon this game and start over?",
+    confirm_model: "Abandon this game and switch mode?",
+    confirm_quit: "Quit the game?",
+    confirm_keys: "y / Enter to confirm, any other key to cancel",
+    games: "Games",
+    wins: "Wins",
+    win_rate: "Win rate",
+    average_score: "Average",
+    median_score: "Median",
+    longest_game: "Longest game",
+    total_moves: "Total moves",
+    highest_tile: "Highest tile",
+    max_tiles: "Max tiles",
+    name: "Name",
+    moves: "Moves",
+    moves_per_second: "Moves/s",
+    duration: "Time",
+    date: "Date",
+    seed: "Seed",
+    achievement_unlocked: "Achievement unlocked",
+    cli_help: &[
+        ("", "A 2048 game to play in the terminal"),
+        ("data-dir", "Data directory"),
+        ("storage", "Storage: sled | json | memory"),
+        ("profile", "Profile to use, defaults to the last one; guest keeps no data"),
+        ("size", "Board size"),
+        ("seed", "Random seed of the first game"),
+        ("theme", "Theme: random | classic | high-contrast | colorblind | mono"),
+        ("animation", "Animation speed: off | slow | normal | fast"),
+        ("big-digits", "Use big digits when tiles are large enough: true | false"),
+        ("graphics", "Draw the board as images: auto | kitty | sixel | off"),
+        ("line", "Print plain lines instead of the full screen UI, for screen readers"),
+        ("json", "Output JSON"),
+        ("play", "Play a game (default)"),
+        ("stats", "Show top scores and stats of each mode"),
+        ("profiles", "List all profiles, * marks the current one"),
+        ("leaderboard", "Show the leaderboard"),
+        ("leaderboard.size", "Board size, defaults to all modes"),
+        ("replay", "Replay a game in the terminal"),
+        ("replay.file", "Replay file, defaults to the last game"),
+        ("replay.delay", "Delay between steps in milliseconds"),
+        ("verify", "Verify the score of a replay file"),
+        ("verify.file", "Replay file"),
+        ("bench", "Make random moves to measure the speed of the game engine"),
+        ("bench.games", "Number of games"),
+        ("bench.seed", "Random seed"),
+        ("export", "Export all data (top scores, saves, replays, settings) as JSON"),
+        ("export.output", "Output file, defaults to standard output"),
+        ("import", "Import data written by export, merged with local data by default"),
+        ("import.file", "Exported file"),
+        ("import.replace", "Replace local data instead of merging"),
+    ],
+    achievement_names: &[
+        ("first_game", "First steps", "Finish your first game"),
+        ("first_2048", "2048!", "Reach 2048 for the first time"),
+        ("tile_4096", "Going further", "Reach 4096 on 4 * 4"),
+        ("perfect_3x3", "Small and perfect", "Reach 512 on 3 * 3 without undo"),
+        ("quick_score", "Quick thinker", "Score 3000 within 250 moves"),
+        ("four_merges", "Quadruple", "Merge four pairs in one move"),
+        ("score_20000", "High roller", "Score 20000 in one game"),
+        ("games_10", "Regular", "Finish 10 games"),
+        ("games_100", "Veteran", "Finish 100 games"),
+    ],
+    new_profile: "+ New profile",
+    profile_name: "Profile name",
+    new_record: "New high score",
+    enter_name: "Type your name and press Enter to save, Esc to skip",
+    imported: "Imported",
+    history: "Undo history",
+    saved_game: "Saved game",
+    replay: "Replay",
+    settings: "Settings",
+    legacy_imported: "Imported data from the previous version:",
+    legacy_import_failed: "Failed to import data from the previous version:",
+    storage_locked: "The database is in use by another t-2048. Progress is kept in memory and top scores and stats will be merged once it is free",
+    storage_unsaved: "The database is still in use by another t-2048. Progress from this session was not saved",
+    read_failed: "Cannot read ",
+    write_failed: "Cannot write ",
+    malformed: "Malformed file: ",
+    upgrade_failed: "Cannot upgrade ",
+    open_db_failed: "Cannot open database ",
+    read_config_failed: "Cannot read config file ",
+    invalid_config: "Invalid config file: ",
+    invalid_imported_config: "The imported config file is invalid",
+    invalid_option: "Invalid option: ",
+    invalid_record: "Invalid record: ",
+    invalid_key_binding: "Invalid key binding: ",
+    unbound_action: "No key is bound to action: ",
+    key_conflict: "Key is bound to more than one action: ",
+    unknown_key: "Unknown key: ",
+    unknown_action: "Unknown action: ",
+    unknown_theme: "Unknown theme: ",
+    unknown_animation: "Unknown animation speed: ",
+    unknown_graphics: "Unknown graphics mode: ",
+    unknown_storage: "Unknown storage: ",
+    unsupported_size: "Unsupported board size: ",
+    choices: ", expected one of ",
+    empty_data_dir: "data_dir must not be empty",
+    empty_profile_name: "Profile name must not be empty",
+    profile_name_too_long: "Profile name is too long, the limit in characters is ",
+    invalid_profile_name: "Profile name must not start with . or contain / \\: ",
+    unsupported_version: "Data version is newer than supported: ",
+    upgrade_hint: ", please upgrade t-2048",
+    invalid_version: "Invalid data version",
+    migration_failed: "Data upgrade failed from version ",
+    no_replay: "No game to replay yet",
+    verify_failed: "Verification failed: ",
+    invalid_undo: "The replay contains an invalid undo",
+    score_mismatch: "Score mismatch: recorded ",
+    recomputed: ", recomputed ",
+    board_mismatch: "The final board does not match the recomputed one",
+};
+
+/// 当前语言的界面文字
+pub fn messages() -> &'static Messages {
+    catalog(config::get().language)
+}
+
+/// 指定语言的界面文字
+pub fn catalog(language: Language) -> &'static Messages {
+    match language {
+        Language::Zh => &ZH,
+        Language::En => &EN,
+    }
+}
+
+impl Messages {
+    /// 成就的名字和说明
+    pub fn achievement(&self, id: &str) -> (&'static str, &'static str) {
+        self.achievement_names
+            .iter()
+            .find(|(i, _, _)| *i == id)
+            .map(|&(_, name, description)| (name, description))
+            .unwrap_or(("", ""))
+    }
+
+    /// 命令行的说明，见 `cli_help`
+    pub fn cli_help(&self, command: &str, arg: &str) -> Option<&'static str> {
+        let find = |key: &str| {
+            self.cli_help
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|&(_, help)| help)
+        };
+        match (command, arg) {
+            (command, "") => find(command),
+            ("", arg) => find(arg),
+            (command, arg) => find(&format!("{}.{}", command, arg)).or_else(|| find(arg)),
+        }
+    }
+
+    /// 移动方向的名称
+    pub fn direction(&self, md: MoveDirection) -> &'static str {
+        match md {
+            MoveDirection::Up => self.up,
+            MoveDirection::Down => self.down,
+            MoveDirection::Left => self.left,
+            MoveDirection::Right => self.right,
+        }
+    }
+
+    /// 操作名称
+    pub fn action(&self, action: Action) -> &'static str {
+        match action {
+            Action::Up => self.up,
+            Action::Down => self.down,
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Restart => self.restart,
+            Action::Undo => self.undo,
+            Action::Hint => self.hint,
+            Action::NextModel => self.next_model,
+            Action::PreviousModel => self.previous_model,
+            Action::Stats => self.stats,
+            Action::Leaderboard => self.leaderboard,
+            Action::Profiles => self.profiles,
+            Action::Achievements => self.achievements,
+            Action::Help => self.help,
+            Action::Pause => self.pause,
+            Action::Quit => self.quit,
+        }
+    }
+}
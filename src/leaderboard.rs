@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::palace::Game;
+use crate::replay::Step;
+use crate::util;
+
+/// 每个模式保留的记录数
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// 名字的最大长度
+pub const NAME_LEN: usize = 16;
+
+/// 排行榜上的一条记录
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Entry {
+    /// 玩家名字
+    pub name: String,
+    /// 得分
+    pub score: u128,
+    /// 最大数字
+    pub max_tile: u128,
+    /// 步数
+    pub moves: u64,
+    /// 用时（秒）
+    pub duration: u64,
+    /// 日期（Unix 秒）
+    pub date: u64,
+    /// 随机种子
+    pub seed: u64,
+    /// 是否撤回过
+    pub undo_used: bool,
+}
+
+impl Entry {
+    /// 由结束的一局生成记录，名字稍后填写
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            name: String::new(),
+            score: game.total_score,
+            max_tile: game.max_tile(),
+            moves: game.moves(),
+            duration: game.elapsed().as_secs(),
+            date: util::now(),
+            seed: game.seed,
+            undo_used: game.steps.contains(&Step::Undo),
+        }
+    }
+}
+
+/// 得分能否进入排行榜
+pub fn qualifies(entries: &[Entry], score: u128) -> bool {
+    score > 0 && (entries.len() < LEADERBOARD_SIZE || score > entries[entries.len() - 1].score)
+}
+
+/// 按得分插入记录，超出的记录被移除，返回名次（从 0 开始）
+pub fn insert(entries: &mut Vec<Entry>, entry: Entry) -> Option<usize> {
+    if !qualifies(entries, entry.score) {
+        return None;
+    }
+    let rank = entries
+        .iter()
+        .position(|e| entry.score > e.score)
+        .unwrap_or(entries.len());
+    entries.insert(rank, entry);
+    entries.truncate(LEADERBOARD_SIZE);
+    Some(rank)
+}
+
+/// 合并两个排行榜，去掉重复的记录
+pub fn merge(entries: &[Entry], other: &[Entry]) -> Vec<Entry> {
+    let mut merged = entries.to_vec();
+    for entry in other {
+        if !merged.contains(entry) {
+            merged.push(entry.clone());
+        }
+    }
+    merged.sort_by(|a, b| b.score.cmp(&a.score).then(a.date.cmp(&b.date)));
+    merged.truncate(LEADERBOARD_SIZE);
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, score: u128) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut entries = Vec::new();
+        assert!(!qualifies(&entries, 0));
+        for score in 1..=LEADERBOARD_SIZE as u128 {
+            assert_eq!(Some(0), insert(&mut entries, entry("a", score * 10)));
+        }
+        assert_eq!(LEADERBOARD_SIZE, entries.len());
+        assert!(!qualifies(&entries, 10));
+        assert_eq!(None, insert(&mut entries, entry("b", 5)));
+
+        assert_eq!(Some(1), insert(&mut entries, entry("b", 95)));
+        assert_eq!(LEADERBOARD_SIZE, entries.len());
+        assert_eq!("b", entries[1].name);
+        assert_eq!(20, entries.last().unwrap().score);
+
+        let merged = merge(&entries, &[entry("c", 1000), entries[0].clone()]);
+        assert_eq!(LEADERBOARD_SIZE, merged.len());
+        assert_eq!("c", merged[0].name);
+        assert_eq!(100, merged[1].score);
+        assert_eq!(30, merged.last().unwrap().score);
+    }
+}
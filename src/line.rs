@@ -0,0 +1,391 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+use crate::achievement::{self, Achievement, Event as GameEvent};
+use crate::cli::{self, Command};
+use crate::config::{self, Action};
+use crate::global::{self, IDLE_PAUSE};
+use crate::i18n::{self, Messages};
+use crate::leaderboard::{Entry, NAME_LEN};
+use crate::palace::{Game, MoveDirection, Moved};
+use crate::profile;
+use crate::store::Store;
+use crate::terminal::TerminalGuard;
+use crate::ui::{self, Pending};
+
+/// 逐行模式：每次操作后输出一句播报和纯文本的宫格，输出留在滚动记录中，方便读屏软件朗读
+///
+/// 选择了其它档案时返回档案名
+pub fn run(game: &mut Game, guard: &TerminalGuard) -> Result<Option<String>> {
+    let config = config::get();
+    let messages = i18n::messages();
+    say(messages.line_mode)?;
+    say(&global::game_description())?;
+    say(&status(game))?;
+
+    let mut last_input = Instant::now();
+    loop {
+        // 游戏结束或长时间没有操作时暂停计时
+        if !game.steps.is_empty() && !game.game_over() && last_input.elapsed() < IDLE_PAUSE {
+            game.resume_clock();
+        } else {
+            game.pause_clock();
+        }
+        let (code, modifiers) = match read_key(guard)? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        last_input = Instant::now();
+        let action = if config::interrupt(code, modifiers) {
+            Some(Action::Quit)
+        } else {
+            config.keys.action(code)
+        };
+        let action = match action {
+            Some(action) => action,
+            None => {
+                say(&sentence(messages, &[messages.unbound_key.to_string()]))?;
+                continue;
+            }
+        };
+        if Pending::Action(action).needs_confirm(game) && !confirm(guard, action)? {
+            continue;
+        }
+        match action {
+            Action::Quit => break,
+            Action::Up | Action::Down | Action::Left | Action::Right => {
+                let md = match action {
+                    Action::Up => MoveDirection::Up,
+                    Action::Down => MoveDirection::Down,
+                    Action::Left => MoveDirection::Left,
+                    _ => MoveDirection::Right,
+                };
+                let before = game.clone();
+                let moved = game.move_palaces(md);
+                say(&describe_move(messages, action, &moved, game))?;
+                // 没有数字方块移动时不算一步
+                if !moved.changed() {
+                    continue;
+                }
+                before.insert_history()?;
+                if game.insert_top_score()? {
+                    say(messages.best_beaten)?;
+                }
+                announce(Store::update_achievements(&GameEvent::Moved(game, &moved))?)?;
+                say(&status(game))?;
+                if game.game_over() {
+                    game.save_replay()?;
+                    say(&sentence(
+                        messages,
+                        &[format!(
+                            "{}{}{} {}",
+                            messages.game_over, messages.comma, messages.score, game.total_score
+                        )],
+                    ))?;
+                    finish(game, guard)?;
+                }
+            }
+            Action::Hint => {
+                if let Some(md) = game.hint() {
+                    say(&sentence(
+                        messages,
+                        &[format!(
+                            "{}{}{}",
+                            messages.hint,
+                            messages.comma,
+                            messages.direction(md)
+                        )],
+                    ))?;
+                }
+            }
+            Action::Help => say(&global::game_description())?,
+            // 暂停计时，等到下一次按键
+            Action::Pause => {
+                game.pause_clock();
+                say(messages.paused)?;
+                if read_key(guard)?.is_none() {
+                    return Ok(None);
+                }
+                last_input = Instant::now();
+            }
+            Action::Undo => {
+                if !game.back()? {
+                    say(messages.nothing_to_undo)?;
+                    continue;
+                }
+                say(messages.undo)?;
+                say(&status(game))?;
+            }
+            Action::Restart | Action::NextModel | Action::PreviousModel => {
+                finish(game, guard)?;
+                match action {
+                    Action::NextModel => game.model.next(),
+                    Action::PreviousModel => game.model.previous(),
+                    _ => {}
+                }
+                game.change_model();
+                let size = game.palaces.len();
+                say(&sentence(
+                    messages,
+                    &[format!("{} {} * {}", messages.model, size, size)],
+                ))?;
+                say(&status(game))?;
+            }
+            Action::Stats => print_command(Command::Stats { json: false })?,
+            Action::Leaderboard => print_command(Command::Leaderboard {
+                size: Some(game.palaces.len()),
+                json: false,
+            })?,
+            Action::Profiles => {
+                print_command(Command::Profiles)?;
+                say(messages.profiles_hint)?;
+                let name = match read_name(guard, messages.profile_name, String::new())? {
+                    Some(name) => name.trim().to_string(),
+                    None => continue,
+                };
+                if name.is_empty() || name == config.profile {
+                    continue;
+                }
+                match profile::validate(&name) {
+                    Ok(()) => return Ok(Some(name)),
+                    Err(e) => say(&e.to_string())?,
+                }
+            }
+            Action::Achievements => say(&achievements(messages)?)?,
+        }
+        // 随时保存，终端断开时进程可能来不及保存就退出
+        game.save()?;
+    }
+    Ok(None)
+}
+
+/// 输出一段文字，原始模式下换行要带上回车
+fn say(text: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}\r\n", text.replace('\n', "\r\n"))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// 把几个短句连成一句话
+fn sentence(messages: &Messages, parts: &[String]) -> String {
+    format!(
+        "{}{}",
+        parts.join(messages.full_stop),
+        messages.full_stop.trim_end()
+    )
+}
+
+/// 宫格和得分
+fn status(game: &Game) -> String {
+    format!(
+        "{}\n{} {}",
+        ui::board_text(&game.palaces),
+        i18n::messages().score,
+        game.total_score
+    )
+}
+
+/// 一步移动的播报：方向、合并出的数字、新数字的位置和得分
+fn describe_move(messages: &Messages, action: Action, moved: &Moved, game: &Game) -> String {
+    let mut parts = vec![messages.action(action).to_string()];
+    if !moved.changed() {
+        parts.push(messages.nothing_moved.to_string());
+    }
+    if !moved.merged.is_empty() {
+        let merged = moved
+            .merged
+            .iter()
+            .map(|&(x, y)| game.palaces[x][y].to_string())
+            .collect::<Vec<_>>()
+            .join(messages.comma);
+        parts.push(format!("{} {}", messages.merged, merged));
+    }
+    if let Some((x, y)) = moved.spawned {
+        parts.push(format!(
+            "{} {}{}{} {}{}{} {}",
+            messages.spawned,
+            game.palaces[x][y],
+            messages.comma,
+            messages.row,
+            x + 1,
+            messages.comma,
+            messages.column,
+            y + 1
+        ));
+    }
+    if moved.gain > 0 {
+        parts.push(format!(
+            "{} {} (+{})",
+            messages.score, game.total_score, moved.gain
+        ));
+    }
+    sentence(messages, &parts)
+}
+
+/// 播报新达成的成就
+fn announce(unlocked: Vec<&Achievement>) -> Result<()> {
+    let messages = i18n::messages();
+    for achievement in unlocked {
+        let (name, _) = messages.achievement(achievement.id);
+        say(&format!(
+            "{}{}{}",
+            messages.achievement_unlocked, messages.comma, name
+        ))?;
+    }
+    Ok(())
+}
+
+/// 全部成就，未达成的附上进度
+fn achievements(messages: &Messages) -> Result<String> {
+    let achievements = Store::achievements()?;
+    let lines = achievement::ALL
+        .iter()
+        .map(|a| {
+            let (name, description) = messages.achievement(a.id);
+            match achievements.unlocked(a) {
+                Some(_) => format!("★ {}{}{}", name, messages.comma, description),
+                None => format!(
+                    "{}{}{}{}{} / {}",
+                    name,
+                    messages.comma,
+                    description,
+                    messages.comma,
+                    achievements.progress(a),
+                    a.target
+                ),
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(format!("{}\n{}", messages.achievements, lines.join("\n")))
+}
+
+/// 临时退出原始模式，输出对应子命令的结果
+fn print_command(command: Command) -> Result<()> {
+    disable_raw_mode()?;
+    let result = cli::run(command);
+    enable_raw_mode()?;
+    result
+}
+
+/// 一局结束：记入统计和成就，得分能进入排行榜时询问名字
+fn finish(game: &mut Game, guard: &TerminalGuard) -> Result<()> {
+    let messages = i18n::messages();
+    let palace_size = game.palaces.len();
+    if !game.finish()? {
+        return Ok(());
+    }
+    announce(Store::update_achievements(&GameEvent::Finished)?)?;
+    if Store::qualifies(palace_size, game.total_score) {
+        let mut entry = Entry::from_game(game);
+        say(&sentence(
+            messages,
+            &[
+                messages.new_record.to_string(),
+                messages.enter_name.to_string(),
+            ],
+        ))?;
+        if let Some(name) = read_name(guard, messages.name, Store::player_name())? {
+            entry.name = name.trim().to_string();
+            if let Some(rank) = Store::insert_leaderboard(palace_size, entry)? {
+                say(&format!("{} #{}", messages.leaderboard, rank + 1))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 逐字读入名字并回显，Esc 跳过
+fn read_name(guard: &TerminalGuard, label: &str, mut name: String) -> Result<Option<String>> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}: {}", label, name)?;
+    stdout.flush()?;
+    let name = loop {
+        let code = match read_key(guard)? {
+            Some((code, _)) => code,
+            None => break None,
+        };
+        match code {
+            KeyCode::Char(c) if name.chars().count() < NAME_LEN => {
+                name.push(c);
+                write!(stdout, "{}", c)?;
+            }
+            KeyCode::Backspace => {
+                if let Some(c) = name.pop() {
+                    // 宽字符占两列
+                    let width = if c.is_ascii() { 1 } else { 2 };
+                    write!(stdout, "{}", "\u{8} \u{8}".repeat(width))?;
+                }
+            }
+            KeyCode::Enter => break Some(name),
+            KeyCode::Esc => break None,
+            _ => {}
+        }
+        stdout.flush()?;
+    };
+    say("")?;
+    Ok(name)
+}
+
+/// 询问是否执行会丢弃当前这一局或退出的操作
+fn confirm(guard: &TerminalGuard, action: Action) -> Result<bool> {
+    let messages = i18n::messages();
+    say(&format!(
+        "{} {}",
+        Pending::Action(action).question(),
+        messages.confirm_keys
+    ))?;
+    // 再按一次 Ctrl-C 也算确认
+    Ok(match read_key(guard)? {
+        Some((KeyCode::Char('y' | 'Y') | KeyCode::Enter, _)) => true,
+        Some((code, modifiers)) => action == Action::Quit && config::interrupt(code, modifiers),
+        None => false,
+    })
+}
+
+/// 等待按键，期间检查是否收到退出信号，收到时返回 `None`
+fn read_key(guard: &TerminalGuard) -> Result<Option<(KeyCode, KeyModifiers)>> {
+    loop {
+        if guard.terminated() {
+            return Ok(None);
+        }
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+                return Ok(Some((code, modifiers)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_describe_move() {
+        let mut game = Game::with_seed(4, 7);
+        game.palaces = vec![vec![2, 2, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]];
+        game.total_score = 0;
+        let moved = game.move_palaces(MoveDirection::Left);
+        let (x, y) = moved.spawned.unwrap();
+        let text = describe_move(&i18n::ZH, Action::Left, &moved, &game);
+        assert_eq!(
+            format!(
+                "左。合并 4。新数字 {}，行 {}，列 {}。得分 4 (+4)。",
+                game.palaces[x][y],
+                x + 1,
+                y + 1
+            ),
+            text
+        );
+
+        game.palaces = vec![vec![2, 4, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]];
+        let moved = game.move_palaces(MoveDirection::Left);
+        let text = describe_move(&i18n::EN, Action::Left, &moved, &game);
+        assert_eq!("Left. Nothing moved.", text);
+    }
+}
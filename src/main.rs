@@ -1,124 +1,788 @@
 #[macro_use]
 extern crate lazy_static;
-#[macro_use]
-extern crate slice_as_array;
 
-use std::io;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
 
-use crossterm::cursor::{Hide, Show};
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent};
-use crossterm::execute;
-use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
-};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use tui::backend::{Backend, CrosstermBackend};
-use tui::layout::{Direction, Layout};
+use tui::layout::{Direction, Layout, Rect};
 use tui::Terminal;
 
-use global::GAME_DESCRIPTION;
-
+use crate::achievement::{Achievement, Achievements, Event as GameEvent};
+use crate::animation::{Animation, Effect, CELEBRATION, FRAME, GAIN};
+use crate::cli::{Cli, Command, PlayArgs};
+use crate::config::{Action, Config, Graphics, Theme, PALACE_SIZES};
+use crate::global::IDLE_PAUSE;
+use crate::graphics::{Protocol, Renderer};
+use crate::leaderboard::{Entry, NAME_LEN};
 use crate::palace::MoveDirection;
+use crate::profile::DEFAULT_PROFILE;
+use crate::stats::Stats;
+use crate::store::Store;
+use crate::terminal::TerminalGuard;
+use crate::ui::Pending;
 
+mod achievement;
+mod animation;
+mod archive;
+mod cli;
+mod config;
+mod digits;
 mod global;
+mod graphics;
+mod i18n;
+mod leaderboard;
+mod line;
+mod mouse;
 mod palace;
+mod profile;
+mod raster;
+mod replay;
+mod schema;
+mod stats;
+mod storage;
 mod store;
+mod terminal;
 mod ui;
 mod util;
 
-fn setup(terminal: &mut Terminal<impl Backend>) -> anyhow::Result<()> {
-    let mut game = palace::Game::new();
+/// 没有指定种子和大小时继续上次未完成的游戏
+fn new_game(play: &PlayArgs) -> palace::Game {
+    let save = match (play.seed, play.size) {
+        (None, None) => Store::save().ok().flatten(),
+        _ => None,
+    };
+    match save {
+        Some(save) => palace::Game::resume(&save),
+        None => palace::Game::new(play.seed),
+    }
+}
+
+/// 覆盖在游戏界面上的弹窗
+enum Overlay {
+    None,
+    /// 各模式的统计，打开时读取一次
+    Stats(Vec<Stats>),
+    Achievements(Achievements),
+    /// 排行榜，高亮刚记入的名次
+    Leaderboard {
+        palace_size: usize,
+        entries: Vec<Entry>,
+        highlight: Option<usize>,
+    },
+    /// 进入排行榜时输入名字
+    NamePrompt {
+        palace_size: usize,
+        name: String,
+        entry: Entry,
+    },
+    /// 帮助，记录滚动到的行
+    Help {
+        scroll: u16,
+    },
+    /// 暂停，不显示宫格
+    Paused,
+    /// 确认会丢弃当前这一局或退出的操作
+    Confirm(Pending),
+    /// 选择档案，选中最后一项时输入新档案的名字
+    Profiles {
+        items: Vec<String>,
+        selected: usize,
+        input: Option<String>,
+    },
+}
+
+/// 成就等提示显示的时间
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// 新达成的成就加入提示
+fn toast(toasts: &mut Vec<(String, Instant)>, unlocked: Vec<&Achievement>) {
+    let messages = i18n::messages();
+    for achievement in unlocked {
+        let (name, _) = messages.achievement(achievement.id);
+        toasts.push((format!("★ {}", name), Instant::now()));
+    }
+}
+
+/// 一局结束：记入统计和成就，得分能进入排行榜时提示输入名字
+fn finish(game: &mut palace::Game, toasts: &mut Vec<(String, Instant)>) -> anyhow::Result<Overlay> {
+    let palace_size = game.palaces.len();
+    if !game.finish()? {
+        return Ok(Overlay::None);
+    }
+    toast(toasts, Store::update_achievements(&GameEvent::Finished)?);
+    if Store::qualifies(palace_size, game.total_score) {
+        return Ok(Overlay::NamePrompt {
+            palace_size,
+            name: Store::player_name(),
+            entry: Entry::from_game(game),
+        });
+    }
+    Ok(Overlay::None)
+}
+
+/// 切换到第 `i` 个模式，先结束当前这一局
+fn select_model(
+    game: &mut palace::Game,
+    toasts: &mut Vec<(String, Instant)>,
+    i: usize,
+) -> anyhow::Result<Overlay> {
+    let overlay = finish(game, toasts)?;
+    game.model.select(i);
+    game.change_model();
+    Ok(overlay)
+}
+
+/// 进行游戏，在菜单中切换档案时返回新档案的名字
+fn setup(
+    terminal: &mut Terminal<impl Backend + Write>,
+    game: &mut palace::Game,
+    guard: &TerminalGuard,
+) -> anyhow::Result<Option<String>> {
+    let config = config::get();
+    let keys = &config.keys;
+    let messages = i18n::messages();
+    let mut overlay = Overlay::None;
+    let mut toasts = Vec::new();
+    let mut last_input = Instant::now();
+    let mut animation: Option<Animation> = None;
+    // 上一步的得分和打破最高分时的得分
+    let mut gain: Option<Effect<u128>> = None;
+    let mut celebration: Option<Effect<u128>> = None;
+    // 提示的方向，下一次操作后清除
+    let mut hint: Option<MoveDirection> = None;
+    // 按下鼠标的位置，松开时判断是拖动还是点击
+    let mut press: Option<(u16, u16)> = None;
+    // 左侧面板显示的当前模式统计，一局结束或切换模式时重新读取
+    let mut stats: Option<(usize, Stats)> = None;
+    // 终端支持时用图片显示宫格，黑白主题下只在明确指定时显示
+    let mut graphics = Protocol::detect(config.graphics)
+        .filter(|_| config.graphics != Graphics::Auto || config.theme != Theme::Mono)
+        .map(Renderer::new);
 
     loop {
+        if animation.as_ref().map_or(false, Animation::finished) {
+            animation = None;
+        }
+        if gain.as_ref().map_or(false, Effect::finished) {
+            gain = None;
+        }
+        if celebration.as_ref().map_or(false, Effect::finished) {
+            celebration = None;
+        }
+        toasts.retain(|(_, at): &(String, Instant)| at.elapsed() < TOAST_DURATION);
+        // 打开弹窗、游戏结束或长时间没有操作时暂停计时
+        let playing = matches!(overlay, Overlay::None)
+            && !game.steps.is_empty()
+            && !game.game_over()
+            && last_input.elapsed() < IDLE_PAUSE;
+        if playing {
+            game.resume_clock();
+        } else {
+            game.pause_clock();
+        }
+        // 终端太小时只显示提示；窄的终端去掉两侧的面板
+        let size = terminal.size()?;
+        let need = ui::min_size(game.palaces.len());
+        let too_small = size.width < need.0 || size.height < need.1;
+        let compact = size.width < ui::FULL_WIDTH || size.height < ui::FULL_HEIGHT;
+        // 弹窗和提示会盖住宫格，这时改用文字显示；sixel 画在屏幕上，要重画整个屏幕才能清除
+        let images = graphics.is_some()
+            && !too_small
+            && matches!(overlay, Overlay::None)
+            && !game.game_over()
+            && celebration.is_none()
+            && toasts.is_empty();
+        if let Some(renderer) = graphics.as_mut().filter(|r| !images && r.is_shown()) {
+            renderer.clear(terminal.backend_mut())?;
+            terminal.clear()?;
+        }
+        // 暂停时不显示宫格
+        let paused = matches!(overlay, Overlay::Paused);
+        let palace_size = game.palaces.len();
+        if stats.as_ref().map(|(size, _)| *size) != Some(palace_size) {
+            stats = Some((
+                palace_size,
+                Store::stats_of(palace_size).unwrap_or_default(),
+            ));
+        }
+        // 宫格、模式列表和按钮的位置，用于响应鼠标
+        let mut board = Rect::default();
+        let mut models = Rect::default();
+        let mut buttons = Vec::new();
         terminal.draw(|f| {
-            let game = &mut game;
+            let game = &mut *game;
+
+            if too_small {
+                ui::too_small(f, need);
+                return;
+            }
+
+            if compact {
+                let chunks = ui::compact_layout(f.size());
+                let status = format!(
+                    "{} {}  {} {}  {} {}  {} {}",
+                    messages.score,
+                    game.total_score,
+                    messages.top_score,
+                    game.top_score,
+                    messages.moves,
+                    game.move_steps,
+                    messages.duration,
+                    util::duration(game.elapsed().as_secs())
+                );
+                let status = match hint {
+                    Some(md) => format!("{}  {} {}", status, messages.hint, ui::arrow(md)),
+                    None => status,
+                };
+                ui::render_paragraph(f, &status, &config.profile, &chunks, 0);
+                board = chunks[1];
+                if !images && !paused {
+                    ui::render_palace(f, &chunks, 1, &game.palaces, animation.as_ref());
+                }
+                if let Some(gain) = &gain {
+                    ui::score_gain(f, &chunks, 0, gain.value, gain.progress());
+                }
+            } else {
+                let chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(ui::crate_percentage_constraint(&[20, 60, 20]))
+                    .split(f.size());
+
+                let left = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(ui::crate_percentage_constraint(&[50; 2]))
+                    .split(chunks[0]);
+
+                if let Some((_, stats)) = &stats {
+                    ui::render_stats(f, left[0], stats);
+                }
 
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(ui::crate_percentage_constraint(&[20, 60, 20]))
-                .split(f.size());
+                ui::render_model(f, &left, 1, game);
+                models = left[1];
 
-            let left = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(ui::crate_percentage_constraint(&[50; 2]))
-                .split(chunks[0]);
+                board = chunks[1];
+                if !images && !paused {
+                    ui::render_palace(f, &chunks, 1, &game.palaces, animation.as_ref());
+                }
 
-            ui::render_description(f, GAME_DESCRIPTION, "游戏说明", &left, 0);
+                let score_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(ui::crate_percentage_constraint(&[30, 30, 30, 10]))
+                    .split(chunks[2]);
+                let score = format!(
+                    "{}\n\n{}  {}\n{}  {}",
+                    game.total_score,
+                    messages.moves,
+                    game.move_steps,
+                    messages.duration,
+                    util::duration(game.elapsed().as_secs())
+                );
+                ui::render_paragraph(f, &score, messages.score, &score_chunks, 0);
 
-            ui::render_model(f, &left, 1, game);
+                ui::render_paragraph(
+                    f,
+                    &game.top_score.to_string(),
+                    &format!("{} · {}", messages.top_score, config.profile),
+                    &score_chunks,
+                    1,
+                );
 
-            ui::render_palace(f, &chunks, 1, &game.palaces);
+                if let Some(gain) = &gain {
+                    ui::score_gain(f, &score_chunks, 0, gain.value, gain.progress());
+                }
 
-            let score_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(ui::crate_percentage_constraint(&[30; 3]))
-                .split(chunks[2]);
-            ui::render_paragraph(f, &game.total_score.to_string(), "得分", &score_chunks, 0);
+                ui::render_progress(f, &score_chunks, 2, game);
 
-            ui::render_paragraph(f, &game.top_score.to_string(), "最高分", &score_chunks, 1);
+                buttons = ui::render_buttons(f, score_chunks[3], hint);
+            }
 
             if game.game_over() {
                 ui::game_over_popup(f, game.total_score);
             }
+            match &overlay {
+                Overlay::None => {}
+                Overlay::Stats(stats) => ui::stats_popup(f, stats, game.palaces.len()),
+                Overlay::Help { scroll } => ui::help_popup(f, *scroll),
+                Overlay::Paused => ui::paused_popup(f, board),
+                Overlay::Confirm(pending) => ui::confirm_popup(f, pending.question()),
+                Overlay::Achievements(achievements) => ui::achievements_popup(f, achievements),
+                Overlay::Leaderboard {
+                    palace_size,
+                    entries,
+                    highlight,
+                } => ui::leaderboard_popup(f, *palace_size, entries, *highlight),
+                Overlay::NamePrompt { name, entry, .. } => ui::name_prompt(f, name, entry.score),
+                Overlay::Profiles {
+                    items,
+                    selected,
+                    input,
+                } => ui::profiles_popup(f, items, &config.profile, *selected, input.as_deref()),
+            }
+            if let Some(celebration) = &celebration {
+                ui::celebration(
+                    f,
+                    celebration.value,
+                    celebration.progress(),
+                    config.animation.duration().is_some(),
+                );
+            }
+            let texts = toasts
+                .iter()
+                .map(|(text, _)| text.clone())
+                .collect::<Vec<_>>();
+            ui::toasts(f, &texts);
+            if config.theme == Theme::Mono {
+                ui::monochrome(f);
+            }
         })?;
+        if let Some(renderer) = graphics.as_mut().filter(|_| images) {
+            let tiles = ui::palace_rects(board, game.palaces.len())
+                .into_iter()
+                .flatten()
+                .zip(game.palaces.iter().flatten().copied())
+                .collect::<Vec<_>>();
+            let screen = terminal.size()?;
+            renderer.draw(terminal.backend_mut(), &tiles, screen)?;
+        }
 
-        if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
-            match code {
-                KeyCode::Esc | KeyCode::Char('q') => break,
-                KeyCode::Char('r') => {
-                    game.change_model();
+        // 等待输入，期间检查是否收到退出信号；播放动画、提示到时间或计时走过一秒时重绘
+        let drawn = Instant::now();
+        let event = loop {
+            if guard.terminated() {
+                return Ok(None);
+            }
+            let animating = animation.is_some() || gain.is_some() || celebration.is_some();
+            let timeout = if animating {
+                FRAME
+            } else {
+                Duration::from_millis(100)
+            };
+            if event::poll(timeout)? {
+                last_input = Instant::now();
+                // 动画还没播完就有新的输入时直接结束动画
+                animation = None;
+                break Some(event::read()?);
+            }
+            if animating
+                || toasts.iter().any(|(_, at)| at.elapsed() >= TOAST_DURATION)
+                || (game.clock_running() && drawn.elapsed() >= Duration::from_secs(1))
+            {
+                break None;
+            }
+        };
+        if let Some(Event::Resize(..)) = event {
+            // 大小变了之后图片的位置和大小都要重新计算，sixel 的图片要清屏才能去掉
+            if let Some(renderer) = graphics.as_mut() {
+                renderer.clear(terminal.backend_mut())?;
+            }
+            terminal.clear()?;
+            continue;
+        }
+        // 在对话框中确认过的操作
+        let mut confirmed = None;
+        let action = match event {
+            Some(Event::Key(KeyEvent { code, modifiers })) => {
+                let interrupt = config::interrupt(code, modifiers);
+                if interrupt {
+                    // 确认退出时再按一次 Ctrl-C 直接退出，其它弹窗先关闭再询问
+                    if matches!(overlay, Overlay::Confirm(Pending::Action(Action::Quit))) {
+                        break;
+                    }
+                    overlay = Overlay::None;
+                }
+                // 终端太小时看不到界面，只响应退出
+                if too_small && !interrupt && keys.action(code) != Some(Action::Quit) {
+                    continue;
+                }
+                match &mut overlay {
+                    Overlay::None => {}
+                    Overlay::Confirm(pending) => {
+                        let pending = *pending;
+                        overlay = Overlay::None;
+                        if !matches!(code, KeyCode::Char('y' | 'Y') | KeyCode::Enter) {
+                            continue;
+                        }
+                        match pending {
+                            Pending::Action(action) => confirmed = Some(action),
+                            Pending::Model(i) => {
+                                overlay = select_model(game, &mut toasts, i)?;
+                                stats = None;
+                                game.save()?;
+                                continue;
+                            }
+                        }
+                    }
+                    Overlay::NamePrompt {
+                        palace_size,
+                        name,
+                        entry,
+                    } => {
+                        match code {
+                            KeyCode::Char(c) if name.chars().count() < NAME_LEN => name.push(c),
+                            KeyCode::Backspace => {
+                                name.pop();
+                            }
+                            KeyCode::Enter => {
+                                let mut entry = entry.clone();
+                                entry.name = name.trim().to_string();
+                                let palace_size = *palace_size;
+                                let highlight = Store::insert_leaderboard(palace_size, entry)?;
+                                overlay = Overlay::Leaderboard {
+                                    palace_size,
+                                    entries: Store::leaderboard_of(palace_size).unwrap_or_default(),
+                                    highlight,
+                                };
+                            }
+                            KeyCode::Esc => overlay = Overlay::None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    Overlay::Profiles {
+                        items,
+                        selected,
+                        input: Some(name),
+                    } => {
+                        match code {
+                            KeyCode::Char(c) if name.chars().count() < NAME_LEN => name.push(c),
+                            KeyCode::Backspace => {
+                                name.pop();
+                            }
+                            // 去掉首尾的空格，只有空格的名字不能保存
+                            KeyCode::Enter if profile::validate(name.trim()).is_ok() => {
+                                return Ok(Some(name.trim().to_string()))
+                            }
+                            KeyCode::Esc => {
+                                overlay = Overlay::Profiles {
+                                    items: items.clone(),
+                                    selected: *selected,
+                                    input: None,
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    Overlay::Profiles {
+                        items,
+                        selected,
+                        input,
+                    } => {
+                        // 最后一项为新建档案
+                        match code {
+                            KeyCode::Up | KeyCode::Char('k') => {
+                                *selected = selected.saturating_sub(1)
+                            }
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                *selected = (*selected + 1).min(items.len())
+                            }
+                            KeyCode::Enter if *selected == items.len() => {
+                                *input = Some(String::new())
+                            }
+                            KeyCode::Enter if items[*selected] == config.profile => {
+                                overlay = Overlay::None
+                            }
+                            KeyCode::Enter => return Ok(Some(items[*selected].clone())),
+                            KeyCode::Esc | KeyCode::Char('q') => overlay = Overlay::None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    Overlay::Help { scroll } => {
+                        let lines = ui::help_text().lines().count() as u16;
+                        match code {
+                            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                *scroll = (*scroll + 1).min(lines.saturating_sub(1))
+                            }
+                            _ => overlay = Overlay::None,
+                        }
+                        continue;
+                    }
+                    // 其它弹窗任意键关闭
+                    _ => {
+                        overlay = Overlay::None;
+                        continue;
+                    }
+                }
+                let action = if interrupt {
+                    Some(Action::Quit)
+                } else {
+                    keys.action(code)
+                };
+                match confirmed.or(action) {
+                    Some(action) => action,
+                    None => continue,
                 }
-                KeyCode::Char('z') => {
-                    game.back()?;
+            }
+            // 有弹窗时不响应鼠标
+            Some(Event::Mouse(MouseEvent {
+                kind, column, row, ..
+            })) if !too_small && matches!(overlay, Overlay::None) => {
+                let at = (column, row);
+                let from = match kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        press = Some(at);
+                        continue;
+                    }
+                    MouseEventKind::Up(_) => match press.take() {
+                        Some(from) => from,
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                // 在宫格上拖动为移动，在同一个按钮上按下和松开为点击
+                if let Some(md) = mouse::swipe(from, at).filter(|_| mouse::contains(board, from)) {
+                    match md {
+                        MoveDirection::Up => Action::Up,
+                        MoveDirection::Down => Action::Down,
+                        MoveDirection::Left => Action::Left,
+                        MoveDirection::Right => Action::Right,
+                    }
+                } else if let Some(&(_, action)) = buttons
+                    .iter()
+                    .find(|(rect, _)| mouse::contains(*rect, from) && mouse::contains(*rect, at))
+                {
+                    action
+                } else {
+                    let count = game.model.items.len();
+                    let selected = mouse::list_item(models, count, at)
+                        .filter(|&i| mouse::list_item(models, count, from) == Some(i));
+                    // 点击模式列表切换到对应的模式
+                    if let Some(i) = selected.filter(|&i| game.model.state.selected() != Some(i)) {
+                        if Pending::Model(i).needs_confirm(game) {
+                            overlay = Overlay::Confirm(Pending::Model(i));
+                        } else {
+                            overlay = select_model(game, &mut toasts, i)?;
+                            stats = None;
+                            game.save()?;
+                        }
+                    }
+                    continue;
                 }
-                KeyCode::Up | KeyCode::Char('k') => {
-                    game.insert_history()?;
-                    game.move_palaces(MoveDirection::Up);
-                    game.insert_top_score()?;
+            }
+            _ => continue,
+        };
+        // 终端太小时看不到对话框，直接退出
+        if confirmed.is_none() && !too_small && Pending::Action(action).needs_confirm(game) {
+            overlay = Overlay::Confirm(Pending::Action(action));
+            continue;
+        }
+        // 提示只显示到下一次操作
+        hint = None;
+        match action {
+            Action::Quit => break,
+            Action::Profiles => {
+                let items = profile::list(&config.data_dir);
+                let selected = items
+                    .iter()
+                    .position(|name| *name == config.profile)
+                    .unwrap_or_default();
+                overlay = Overlay::Profiles {
+                    items,
+                    selected,
+                    input: None,
+                }
+            }
+            Action::Restart => {
+                overlay = finish(game, &mut toasts)?;
+                stats = None;
+                game.change_model();
+            }
+            Action::Undo => {
+                if !game.back()? {
+                    toasts.push((messages.nothing_to_undo.to_string(), Instant::now()));
                 }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    game.insert_history()?;
-                    game.move_palaces(MoveDirection::Down);
-                    game.insert_top_score()?;
+            }
+            Action::Hint => hint = game.hint(),
+            Action::Help => overlay = Overlay::Help { scroll: 0 },
+            Action::Pause => overlay = Overlay::Paused,
+            Action::Up | Action::Down | Action::Left | Action::Right => {
+                let md = match action {
+                    Action::Up => MoveDirection::Up,
+                    Action::Down => MoveDirection::Down,
+                    Action::Left => MoveDirection::Left,
+                    _ => MoveDirection::Right,
+                };
+                let before = game.clone();
+                let moved = game.move_palaces(md);
+                // 没有数字方块移动时不算一步
+                if !moved.changed() {
+                    continue;
                 }
-                KeyCode::Left | KeyCode::Char('h') => {
-                    game.insert_history()?;
-                    game.move_palaces(MoveDirection::Left);
-                    game.insert_top_score()?;
+                before.insert_history()?;
+                if game.insert_top_score()? {
+                    celebration = Some(Effect::new(game.total_score, CELEBRATION));
                 }
-                KeyCode::Right | KeyCode::Char('l') => {
-                    game.insert_history()?;
-                    game.move_palaces(MoveDirection::Right);
-                    game.insert_top_score()?;
+                if moved.gain > 0 {
+                    gain = Some(Effect::new(moved.gain, GAIN));
                 }
-                KeyCode::Char('m') => {
-                    game.model.next();
-                    game.change_model();
+                let unlocked = Store::update_achievements(&GameEvent::Moved(game, &moved))?;
+                toast(&mut toasts, unlocked);
+                // 用图片显示宫格时没有移动动画
+                animation = config
+                    .animation
+                    .duration()
+                    .filter(|_| graphics.is_none())
+                    .map(|duration| Animation::new(moved, duration));
+                if game.game_over() {
+                    game.save_replay()?;
+                    overlay = finish(game, &mut toasts)?;
+                    stats = None;
                 }
-                KeyCode::Char('M') => {
-                    game.model.previous();
-                    game.change_model();
+            }
+            Action::NextModel => {
+                overlay = finish(game, &mut toasts)?;
+                stats = None;
+                game.model.next();
+                game.change_model();
+            }
+            Action::PreviousModel => {
+                overlay = finish(game, &mut toasts)?;
+                stats = None;
+                game.model.previous();
+                game.change_model();
+            }
+            Action::Stats => {
+                let stats = PALACE_SIZES
+                    .iter()
+                    .map(|&size| Store::stats_of(size).unwrap_or_default())
+                    .collect();
+                overlay = Overlay::Stats(stats);
+            }
+            Action::Achievements => {
+                overlay = Overlay::Achievements(Store::achievements().unwrap_or_default())
+            }
+            Action::Leaderboard => {
+                overlay = Overlay::Leaderboard {
+                    palace_size: game.palaces.len(),
+                    entries: Store::leaderboard_of(game.palaces.len()).unwrap_or_default(),
+                    highlight: None,
                 }
-                _ => {}
             }
         }
+        // 随时保存，终端断开时进程可能来不及保存就退出
+        game.save()?;
+    }
+
+    Ok(None)
+}
+
+/// 打开存储并导入当前目录下旧版本的数据，导入失败时不影响游戏
+fn open_store() -> anyhow::Result<()> {
+    Store::init()?;
+    let messages = i18n::messages();
+    if Store::is_locked() {
+        eprintln!("{}", messages.storage_locked);
+    }
+    match Store::import_legacy() {
+        Ok(Some(legacy)) => eprintln!("{} {}", messages.legacy_imported, legacy.display()),
+        Ok(None) => {}
+        Err(e) => eprintln!("{} {:#}", messages.legacy_import_failed, e),
     }
+    Ok(())
+}
 
+/// 退出前写入磁盘，数据库仍被占用时提示数据没有保存
+fn close_store() -> anyhow::Result<()> {
+    Store::flush()?;
+    if Store::is_locked() {
+        eprintln!("{}", i18n::messages().storage_unsaved);
+    }
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    enable_raw_mode()?;
+/// 读取配置并切换到指定的档案，未指定时使用上次的档案
+fn load_config(cli: &Cli, profile: Option<&str>, play: &PlayArgs) -> anyhow::Result<Config> {
+    let mut config = Config::load()?;
+    if let Some(data_dir) = &cli.data_dir {
+        config.data_dir = data_dir.clone();
+    }
+    if let Some(storage) = cli.storage {
+        config.storage = storage;
+    }
+    let profile = profile
+        .map(str::to_string)
+        .or_else(|| cli.profile.clone())
+        .or_else(|| profile::last(&config.data_dir))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    config.use_profile(&profile)?;
+    play.apply(&mut config)?;
+    Ok(config)
+}
+
+/// 切换档案：换成新档案的配置和存储，继续新档案未完成的游戏
+fn switch_profile(cli: &Cli, profile: &str) -> anyhow::Result<palace::Game> {
+    Store::flush()?;
+    let config = config::init(load_config(cli, Some(profile), &cli.play)?);
+    profile::remember(&config.data_dir, &config.profile)?;
+    Store::init()?;
+    unsafe {
+        global::PALACE_SIZE = config.size;
+    }
+    Ok(new_game(&PlayArgs::default()))
+}
 
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, Hide, EnableMouseCapture)?;
+fn main() -> anyhow::Result<()> {
+    let mut cli = cli::parse();
 
-    let backend = CrosstermBackend::new(io::stdout());
-    let mut terminal = Terminal::new(backend)?;
+    let play = match cli.command.take() {
+        None => std::mem::take(&mut cli.play),
+        Some(Command::Play(play)) => play,
+        Some(command) => {
+            config::init(load_config(&cli, None, &PlayArgs::default())?);
+            open_store()?;
+            cli::run(command)?;
+            return Store::flush();
+        }
+    };
+    cli.play = play;
+    let config = config::init(load_config(&cli, None, &cli.play)?);
+    profile::remember(&config.data_dir, &config.profile)?;
+    open_store()?;
+    unsafe {
+        global::PALACE_SIZE = config.size;
+    }
 
-    setup(&mut terminal)?;
+    let mut game = new_game(&cli.play);
 
-    execute!(stdout, LeaveAlternateScreen, Show, DisableMouseCapture)?;
-    disable_raw_mode()?;
+    let on_hangup = || {
+        let _ = close_store();
+    };
+    let guard = if config.line_mode {
+        TerminalGuard::enter_line(on_hangup)?
+    } else {
+        TerminalGuard::enter(on_hangup)?
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        if config.line_mode {
+            while let Some(profile) = line::run(&mut game, &guard)? {
+                game.save_replay()?;
+                game.save()?;
+                game = switch_profile(&cli, &profile)?;
+            }
+            return Ok(());
+        }
+        let backend = CrosstermBackend::new(io::stdout());
+        let mut terminal = Terminal::new(backend)?;
+        // 在菜单中切换档案时先保存当前这一局
+        while let Some(profile) = setup(&mut terminal, &mut game, &guard)? {
+            game.save_replay()?;
+            game.save()?;
+            game = switch_profile(&cli, &profile)?;
+        }
+        Ok(())
+    }));
+    drop(guard);
 
-    Ok(())
+    // 无论是正常退出、出错、panic 还是收到信号，都保存当前这一局
+    let saved = game.save_replay().and_then(|_| game.save());
+    let closed = close_store();
+    match result {
+        Ok(result) => result.and(saved).and(closed),
+        Err(panic) => panic::resume_unwind(panic),
+    }
 }
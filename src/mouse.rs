@@ -0,0 +1,68 @@
+use tui::layout::Rect;
+
+use crate::palace::MoveDirection;
+
+/// 拖动的距离不到这么多列时算作点击，纵向按两倍计算
+const MIN_SWIPE: u16 = 3;
+
+/// 点是否在区域内
+pub fn contains(area: Rect, (column, row): (u16, u16)) -> bool {
+    column >= area.left() && column < area.right() && row >= area.top() && row < area.bottom()
+}
+
+/// 拖动对应的移动方向，距离太短时为 `None`。终端的格子高约为宽的两倍，纵向距离按两倍计算
+pub fn swipe(from: (u16, u16), to: (u16, u16)) -> Option<MoveDirection> {
+    let dx = to.0 as i32 - from.0 as i32;
+    let dy = (to.1 as i32 - from.1 as i32) * 2;
+    if dx.abs().max(dy.abs()) < MIN_SWIPE as i32 {
+        return None;
+    }
+    Some(if dx.abs() >= dy.abs() {
+        if dx > 0 {
+            MoveDirection::Right
+        } else {
+            MoveDirection::Left
+        }
+    } else if dy > 0 {
+        MoveDirection::Down
+    } else {
+        MoveDirection::Up
+    })
+}
+
+/// 点中了带边框的列表中的第几项
+pub fn list_item(area: Rect, count: usize, at: (u16, u16)) -> Option<usize> {
+    let inner = Rect::new(
+        area.x + 1,
+        area.y + 1,
+        area.width.saturating_sub(2),
+        area.height.saturating_sub(2),
+    );
+    if !contains(inner, at) {
+        return None;
+    }
+    Some((at.1 - inner.y) as usize).filter(|&i| i < count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_swipe() {
+        assert_eq!(None, swipe((10, 10), (10, 10)));
+        assert_eq!(None, swipe((10, 10), (12, 11)));
+        assert_eq!(Some(MoveDirection::Right), swipe((10, 10), (16, 12)));
+        assert_eq!(Some(MoveDirection::Left), swipe((10, 10), (7, 10)));
+        assert_eq!(Some(MoveDirection::Up), swipe((10, 10), (11, 8)));
+        assert_eq!(Some(MoveDirection::Down), swipe((10, 10), (13, 12)));
+
+        let area = Rect::new(2, 5, 20, 6);
+        assert!(contains(area, (2, 5)));
+        assert!(!contains(area, (22, 5)));
+        assert_eq!(None, list_item(area, 4, (3, 5)));
+        assert_eq!(Some(0), list_item(area, 4, (3, 6)));
+        assert_eq!(Some(3), list_item(area, 4, (3, 9)));
+        assert_eq!(None, list_item(area, 2, (3, 9)));
+    }
+}
@@ -1,19 +1,26 @@
 use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use global::PALACE_SIZE;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use tui::widgets::{Borders, Widget};
 use tui::{buffer::Buffer, style::Color};
 use tui::{layout::Rect, widgets::BorderType};
 use tui::{style::Style, widgets::ListState};
 
+use crate::config::{self, Theme};
+use crate::digits;
 use crate::global;
+use crate::replay::{Replay, Step};
+use crate::stats::win_tile;
 use crate::store::Store;
 use crate::util;
 
 /// 移动方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MoveDirection {
     Up,
     Down,
@@ -21,9 +28,39 @@ pub enum MoveDirection {
     Right,
 }
 
-///
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Game<'a> {
+/// 一次移动的结果，也用于动画
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Moved {
+    /// 得分
+    pub gain: u128,
+    /// 合并的次数
+    pub merges: usize,
+    /// 每个数字方块的移动
+    pub slides: Vec<Slide>,
+    /// 合并出新数字的格子
+    pub merged: Vec<(usize, usize)>,
+    /// 新生成数字的格子
+    pub spawned: Option<(usize, usize)>,
+}
+
+impl Moved {
+    /// 是否有数字方块移动或合并
+    pub fn changed(&self) -> bool {
+        self.slides.iter().any(|slide| slide.from != slide.to)
+    }
+}
+
+/// 一个数字方块从哪一格移到哪一格，`num` 为移动前的数字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slide {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub num: u128,
+}
+
+/// 游戏状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
     /// 宫格数字
     pub palaces: Vec<Vec<u128>>,
     /// 总分
@@ -34,27 +71,51 @@ pub struct Game<'a> {
     pub move_steps: u128,
     /// 结束    
     pub game_over: bool,
+    /// 随机种子
+    #[serde(default)]
+    pub seed: u64,
+    /// 本局用时（毫秒），不含暂停的时间
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// 计时开始的时间，暂停时为空
+    #[serde(skip)]
+    clock: Option<Instant>,
+    /// 本局的操作记录
+    #[serde(skip)]
+    pub steps: Vec<Step>,
+    /// 每步之后的得分，撤回时一起撤回
+    #[serde(skip)]
+    pub scores: Vec<u128>,
     /// 模式
     #[serde(skip)]
-    pub model: Model<'a>,
+    pub model: Model,
+    /// 本局已记入统计
+    #[serde(skip)]
+    recorded: bool,
+    /// 本局已打破之前的最高分
+    #[serde(skip)]
+    new_record: bool,
+    /// 由种子生成的随机数
+    #[serde(skip, default = "util::rng")]
+    rng: StdRng,
 }
 
 /// n * n 模式
-#[derive(Debug, Default)]
-pub struct Model<'a> {
+#[derive(Debug, Clone, Default)]
+pub struct Model {
     pub state: ListState,
-    pub items: Vec<(&'a str, usize)>,
+    pub items: Vec<(&'static str, usize)>,
 }
 
-impl<'a> Model<'a> {
+impl Model {
     pub fn new() -> Self {
+        let items = vec![("3 * 3", 3), ("4 * 4", 4), ("5 * 5", 5), ("6 * 6", 6)];
+        let palace_size = unsafe { PALACE_SIZE };
+
         let mut ls = ListState::default();
-        ls.select(Some(1));
+        ls.select(items.iter().position(|&(_, size)| size == palace_size));
 
-        Self {
-            state: ls,
-            items: vec![("3 * 3", 3), ("4 * 4", 4), ("5 * 5", 5), ("6 * 6", 6)],
-        }
+        Self { state: ls, items }
     }
 
     pub fn next(&mut self) {
@@ -68,10 +129,7 @@ impl<'a> Model<'a> {
             }
             None => 0,
         };
-        self.state.select(Some(i));
-        unsafe {
-            PALACE_SIZE = self.items[i].1;
-        }
+        self.select(i);
     }
 
     pub fn previous(&mut self) {
@@ -85,6 +143,11 @@ impl<'a> Model<'a> {
             }
             None => 0,
         };
+        self.select(i);
+    }
+
+    /// 选中第 `i` 个模式
+    pub fn select(&mut self, i: usize) {
         self.state.select(Some(i));
         unsafe {
             PALACE_SIZE = self.items[i].1;
@@ -92,32 +155,61 @@ impl<'a> Model<'a> {
     }
 }
 
-impl<'a> Game<'a> {
-    pub fn new() -> Self {
+impl Game {
+    /// 新游戏，未指定种子时随机生成
+    pub fn new(seed: Option<u64>) -> Self {
+        let palace_size = unsafe { PALACE_SIZE };
+        let mut game = Self::with_seed(palace_size, seed.unwrap_or_else(rand::random));
+        game.top_score = Self::top_score();
+        game
+    }
+
+    /// 从存档恢复，宫格大小随存档改变
+    pub fn resume(save: &Replay) -> Self {
+        unsafe {
+            PALACE_SIZE = save.size;
+        }
+        let mut game = save.play(|_, _, _| {});
+        game.top_score = Self::top_score();
+        game.elapsed_ms = save.elapsed_ms;
+        game
+    }
+
+    /// 由种子确定的游戏，不读取存储
+    pub fn with_seed(palace_size: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
         Self {
-            palaces: Self::init_palace(),
+            palaces: Self::init_palace(palace_size, &mut rng),
             total_score: 0,
-            top_score: Self::top_score(),
+            top_score: 0,
             move_steps: 0,
             game_over: false,
+            seed,
+            elapsed_ms: 0,
+            clock: None,
+            steps: Vec::new(),
+            scores: Vec::new(),
             model: Model::new(),
+            recorded: false,
+            new_record: false,
+            rng,
         }
     }
 
     /// 初始化宫格数字
-    fn init_palace() -> Vec<Vec<u128>> {
-        let mut palaces = unsafe { vec![vec![0; PALACE_SIZE]; PALACE_SIZE] };
+    fn init_palace(palace_size: usize, rng: &mut StdRng) -> Vec<Vec<u128>> {
+        let mut palaces = vec![vec![0; palace_size]; palace_size];
         let mut index = 0;
         loop {
             if index >= 2 {
                 break;
             }
-            let position = util::position();
+            let position = util::position(palace_size, rng);
 
             for (x, xp) in palaces.iter_mut().enumerate() {
                 for (y, yp) in xp.iter_mut().enumerate() {
                     if position.0 == x && position.1 == y {
-                        *yp = util::two_or_four();
+                        *yp = util::two_or_four(rng);
                         index += 1;
                     }
                 }
@@ -129,11 +221,122 @@ impl<'a> Game<'a> {
 
     /// 改变模式
     pub fn change_model(&mut self) {
-        self.palaces = Self::init_palace();
+        let _ = self.finish();
+        let _ = self.save_replay();
+        let _ = Store::remove_history();
+
+        let palace_size = unsafe { PALACE_SIZE };
+        self.seed = rand::random();
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.palaces = Self::init_palace(palace_size, &mut self.rng);
         self.total_score = 0;
         self.top_score = Self::top_score();
         self.move_steps = 0;
         self.game_over = false;
+        self.elapsed_ms = 0;
+        self.clock = None;
+        self.steps.clear();
+        self.scores.clear();
+        self.recorded = false;
+        self.new_record = false;
+    }
+
+    /// 保存未完成的游戏，已结束的游戏删除存档
+    pub fn save(&self) -> Result<()> {
+        if self.steps.is_empty() || self.game_over() {
+            Store::remove_save()
+        } else {
+            Store::insert_save(&Replay::from_game(self))
+        }
+    }
+
+    /// 一局结束时记入统计，每局只记一次，返回这次是否记录了
+    pub fn finish(&mut self) -> Result<bool> {
+        if self.recorded || self.steps.is_empty() {
+            return Ok(false);
+        }
+        Store::record_stats(self)?;
+        self.recorded = true;
+        Ok(true)
+    }
+
+    /// 保存本局的回放
+    pub fn save_replay(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            return Ok(());
+        }
+        Store::insert_replay(&Replay::from_game(self))
+    }
+
+    /// 移动的步数，不含撤回
+    pub fn moves(&self) -> u64 {
+        self.steps
+            .iter()
+            .filter(|s| s.direction().is_some())
+            .count() as u64
+    }
+
+    /// 开始或继续计时
+    pub fn resume_clock(&mut self) {
+        if self.clock.is_none() {
+            self.clock = Some(Instant::now());
+        }
+    }
+
+    /// 暂停计时
+    pub fn pause_clock(&mut self) {
+        if let Some(start) = self.clock.take() {
+            self.elapsed_ms += start.elapsed().as_millis() as u64;
+        }
+    }
+
+    /// 是否正在计时
+    pub fn clock_running(&self) -> bool {
+        self.clock.is_some()
+    }
+
+    /// 本局用时
+    pub fn elapsed(&self) -> Duration {
+        let running = self.clock.map(|start| start.elapsed()).unwrap_or_default();
+        Duration::from_millis(self.elapsed_ms) + running
+    }
+
+    /// 宫格中最大的数字
+    pub fn max_tile(&self) -> u128 {
+        self.palaces.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// 上一步的得分
+    pub fn last_gain(&self) -> u128 {
+        match self.scores.as_slice() {
+            [.., before, last] => last - before,
+            [last] => *last,
+            [] => 0,
+        }
+    }
+
+    /// 下一个目标：先是该尺寸的获胜数字，之后是最大数字的两倍
+    pub fn target_tile(&self) -> u128 {
+        let max_tile = self.max_tile();
+        let win_tile = win_tile(self.palaces.len());
+        if max_tile < win_tile {
+            win_tile
+        } else {
+            max_tile * 2
+        }
+    }
+
+    /// 宫格中每个数字的个数，按数字从小到大
+    pub fn tile_counts(&self) -> Vec<(u128, usize)> {
+        let mut counts: Vec<(u128, usize)> = Vec::new();
+        for &num in self.palaces.iter().flatten().filter(|&&num| num != 0) {
+            match counts.iter_mut().find(|(n, _)| *n == num) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((num, 1)),
+            }
+        }
+        counts.sort_unstable();
+        counts
     }
 
     /// 游戏是否结束
@@ -183,79 +386,121 @@ impl<'a> Game<'a> {
         false
     }
 
-    /// 移动
-    pub fn move_palaces(&mut self, md: MoveDirection) {
-        let palace_size = unsafe { PALACE_SIZE };
-        match md {
-            MoveDirection::Up => {
-                for x in 0..palace_size {
-                    let mut o = Vec::new();
-                    for y in 0..palace_size {
-                        o.push(self.palaces[y][x]);
-                    }
-                    self.merge(&o).iter().enumerate().for_each(|(y, yv)| {
-                        self.palaces[y][x] = *yv;
-                    });
-                }
-            }
-            MoveDirection::Down => {
-                for x in 0..palace_size {
-                    let mut o = Vec::new();
-                    for y in 0..palace_size {
-                        o.push(self.palaces[palace_size - y - 1][x]);
-                    }
-                    self.merge(&o.into_iter().collect::<Vec<_>>())
-                        .iter()
-                        .enumerate()
-                        .for_each(|(y, yv)| {
-                            self.palaces[palace_size - y - 1][x] = *yv;
-                        });
-                }
-            }
-            MoveDirection::Left => {
-                (0..palace_size).for_each(|i| {
-                    self.palaces[i] = self.merge(&self.palaces[i].clone());
-                });
+    /// 移动，没有数字方块移动或合并时不算一步，也不生成新数字
+    pub fn move_palaces(&mut self, md: MoveDirection) -> Moved {
+        let palace_size = self.palaces.len();
+        let (score, tiles) = (self.total_score, self.tiles());
+        let mut slides = Vec::new();
+        let mut merged = Vec::new();
+        // 每一行（列）按移动方向排列成向左合并的顺序
+        for i in 0..palace_size {
+            let cells = (0..palace_size)
+                .map(|j| match md {
+                    MoveDirection::Left => (i, j),
+                    MoveDirection::Right => (i, palace_size - j - 1),
+                    MoveDirection::Up => (j, i),
+                    MoveDirection::Down => (palace_size - j - 1, i),
+                })
+                .collect::<Vec<_>>();
+            let line = cells
+                .iter()
+                .map(|&(x, y)| self.palaces[x][y])
+                .collect::<Vec<_>>();
+            let (merged_line, moves) = self.merge_line(&line);
+            for (&(x, y), &num) in cells.iter().zip(&merged_line) {
+                self.palaces[x][y] = num;
             }
-            MoveDirection::Right => {
-                (0..palace_size).for_each(|i| {
-                    self.palaces[i] = self
-                        .merge(&self.palaces[i].iter().rev().cloned().collect::<Vec<_>>())
-                        .iter()
-                        .rev()
-                        .cloned()
-                        .collect::<Vec<_>>();
+            for (from, to, merge) in moves {
+                slides.push(Slide {
+                    from: cells[from],
+                    to: cells[to],
+                    num: line[from],
                 });
+                if merge {
+                    merged.push(cells[to]);
+                }
             }
         }
 
-        self.empty_position_gen();
+        // 每次合并少一个数字
+        let mut moved = Moved {
+            gain: self.total_score - score,
+            merges: tiles - self.tiles(),
+            slides,
+            merged,
+            spawned: None,
+        };
+        if !moved.changed() {
+            return moved;
+        }
+        self.steps.push(Step::from(md));
+        self.move_steps += 1;
+        self.scores.push(self.total_score);
+        moved.spawned = self.empty_position_gen();
+        moved
+    }
+
+    /// 提示下一步：试走每个方向，选得分最多的，得分相同时选留下空位最多的
+    pub fn hint(&self) -> Option<MoveDirection> {
+        [
+            MoveDirection::Up,
+            MoveDirection::Down,
+            MoveDirection::Left,
+            MoveDirection::Right,
+        ]
+        .iter()
+        .filter_map(|&md| {
+            let mut game = self.clone();
+            let moved = game.move_palaces(md);
+            moved
+                .changed()
+                .then(|| (moved.gain, game.empty_position().len(), md))
+        })
+        .max_by_key(|&(gain, empty, _)| (gain, empty))
+        .map(|(_, _, md)| md)
     }
 
-    /// 在宫格剩余空位置上生成方块
-    fn empty_position_gen(&mut self) {
+    /// 非空的格子数
+    fn tiles(&self) -> usize {
+        self.palaces
+            .iter()
+            .flatten()
+            .filter(|&&num| num != 0)
+            .count()
+    }
+
+    /// 在宫格剩余空位置上生成方块，返回生成的位置
+    fn empty_position_gen(&mut self) -> Option<(usize, usize)> {
         let palace_empty = self.empty_position();
 
         if palace_empty.is_empty() {
-            return;
+            return None;
         }
 
         // 如果有空位置，在空位置随机生成一个数字方块
-        let position_at_index = rand::thread_rng().gen_range(0..palace_empty.len());
+        let position_at_index = self.rng.gen_range(0..palace_empty.len());
         let (position_x, position_y) = palace_empty[position_at_index];
-        self.palaces[position_x][position_y] = util::two_or_four();
+        self.palaces[position_x][position_y] = util::two_or_four(&mut self.rng);
+        Some((position_x, position_y))
     }
 
     /// 向左合并数字
+    #[cfg(test)]
     fn merge(&mut self, vs: &[u128]) -> Vec<u128> {
-        let palace_size = unsafe { PALACE_SIZE };
-        let mut q = vs.iter().collect::<VecDeque<_>>();
+        self.merge_line(vs).0
+    }
+
+    /// 向左合并数字，同时返回每个数字从哪一格移到哪一格，以及是否是合并
+    fn merge_line(&mut self, vs: &[u128]) -> (Vec<u128>, Vec<(usize, usize, bool)>) {
+        let palace_size = vs.len();
+        let mut q = vs.iter().enumerate().collect::<VecDeque<_>>();
         // 存放合并之后的值的队列
         let mut cvq = VecDeque::new();
+        let mut moves = Vec::new();
         // 是否合并过
         let mut merged = false;
         while !q.is_empty() {
-            let qv = q.pop_front().unwrap();
+            let (i, qv) = q.pop_front().unwrap();
             if *qv != 0 {
                 if let Some(cv) = cvq.back() {
                     if *cv == *qv && !merged {
@@ -272,6 +517,7 @@ impl<'a> Game<'a> {
                     cvq.push_back(*qv);
                     merged = false;
                 }
+                moves.push((i, cvq.len() - 1, merged));
             }
         }
 
@@ -282,7 +528,7 @@ impl<'a> Game<'a> {
             });
         }
 
-        cvq.into_iter().collect::<Vec<_>>()
+        (cvq.into_iter().collect::<Vec<_>>(), moves)
     }
 
     /// 最高分
@@ -290,13 +536,16 @@ impl<'a> Game<'a> {
         Store::top_score().unwrap_or_default()
     }
 
-    /// 最高分
-    pub fn insert_top_score(&mut self) -> Result<()> {
-        if self.total_score > self.top_score {
-            self.top_score = self.total_score;
-            let _ = Store::insert_top_score(self.total_score);
+    /// 最高分，本局第一次超过之前的最高分时返回 `true`
+    pub fn insert_top_score(&mut self) -> Result<bool> {
+        if self.total_score <= self.top_score {
+            return Ok(false);
         }
-        Ok(())
+        let beaten = !self.new_record && self.top_score > 0;
+        self.new_record = true;
+        self.top_score = self.total_score;
+        let _ = Store::insert_top_score(self.total_score);
+        Ok(beaten)
     }
 
     /// 记录移动之前的状态
@@ -305,18 +554,19 @@ impl<'a> Game<'a> {
         Ok(())
     }
 
-    /// 撤回
-    pub fn back(&mut self) -> Result<()> {
+    /// 撤回，没有可以撤回的操作时返回 `false`
+    pub fn back(&mut self) -> Result<bool> {
         if let Some(history) = Store::history()? {
             self.palaces = history.palaces;
             self.total_score = history.total_score;
             self.top_score = Self::top_score();
             self.move_steps = history.move_steps;
             self.game_over = history.game_over;
-
-            Store::remove_history()?;
+            self.scores.truncate(self.move_steps as usize);
+            self.steps.push(Step::Undo);
+            return Ok(true);
         }
-        Ok(())
+        Ok(false)
     }
 }
 
@@ -326,6 +576,10 @@ pub struct Palace {
     borders: Borders,
     border_style: Style,
     border_type: BorderType,
+    /// 淡入的进度，1 为完全显示
+    fade: f64,
+    /// 格子够大时用大号数字
+    big_digits: bool,
 }
 
 impl Default for Palace {
@@ -335,6 +589,8 @@ impl Default for Palace {
             borders: Borders::ALL,
             border_style: Style::default(),
             border_type: BorderType::Rounded,
+            fade: 1.0,
+            big_digits: false,
         }
     }
 }
@@ -345,6 +601,16 @@ impl Palace {
         self
     }
 
+    pub fn fade(mut self, fade: f64) -> Palace {
+        self.fade = fade;
+        self
+    }
+
+    pub fn big_digits(mut self, big_digits: bool) -> Palace {
+        self.big_digits = big_digits;
+        self
+    }
+
     // pub fn borders(mut self, borders: Borders) -> Palace {
     //     self.borders = borders;
     //     self
@@ -363,11 +629,28 @@ impl Palace {
 
 impl Widget for Palace {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        // 放不下边框时不画，否则下面计算右边和下边时会溢出
+        if area.width < 2 || area.height < 2 {
+            return;
+        }
         let mut palace_color = global::PALACE_COLOR.lock().unwrap();
-        if self.num != 0 && palace_color.contains_key(&self.num) {
+        let mut text_style = Style::default().fg(Color::White);
+        if self.num != 0 && config::get().theme == Theme::Mono {
+            // 黑白主题用反色和底纹区分数字，淡入过半后才显示
+            let (modifier, fill) = util::mono_style(self.num);
+            text_style = Style::default().add_modifier(modifier);
+            if self.fade >= 0.5 {
+                for y in area.top() + 1..area.bottom() - 1 {
+                    for x in area.left() + 2..area.right() - 2 {
+                        buf.get_mut(x, y).set_symbol(fill).set_style(text_style);
+                    }
+                }
+            }
+        } else if self.num != 0 && palace_color.contains_key(&self.num) {
             let style;
             if let Some(&c) = palace_color.get(&self.num) {
-                style = Style::default().bg(c);
+                style = Style::default().bg(util::fade(c, self.fade));
+                text_style = Style::default().fg(util::text_color(c));
             } else {
                 style = self.border_style;
             }
@@ -378,7 +661,7 @@ impl Widget for Palace {
                 }
             }
         } else {
-            palace_color.insert(self.num, util::color());
+            palace_color.insert(self.num, util::num_color(self.num));
         }
 
         let symbols = BorderType::line_symbols(self.border_type);
@@ -437,19 +720,31 @@ impl Widget for Palace {
                 .set_style(self.border_style);
         }
 
-        let text = {
-            if self.num == 0 {
-                "".to_string()
-            } else {
-                self.num.to_string()
+        if self.num == 0 {
+            return;
+        }
+
+        // 边框和左右各留一列空白之内放得下时画大号数字
+        let style = text_style;
+        let width = digits::width(self.num);
+        if self.big_digits && area.width >= width + 4 && area.height >= digits::HEIGHT + 2 {
+            let x = area.left() + (area.width - width) / 2;
+            let y = area.top() + (area.height - digits::HEIGHT) / 2;
+            for (i, line) in digits::lines(self.num).into_iter().enumerate() {
+                buf.set_string(x, y + i as u16, line, style);
             }
-        };
+            return;
+        }
 
-        buf.set_string(
-            area.left() + area.width / 2 - text.chars().count() as u16 / 2,
+        // 格子太窄时截掉放不下的部分，不画到相邻的格子上
+        let text = self.num.to_string();
+        let width = (text.len() as u16).min(area.width);
+        buf.set_stringn(
+            area.left() + (area.width / 2).saturating_sub(width / 2),
             area.top() + area.height / 2,
             text,
-            Style::default().fg(Color::White),
+            width as usize,
+            style,
         );
     }
 }
@@ -459,8 +754,126 @@ mod test {
     use super::*;
 
     #[test]
+    fn test_progress() {
+        let mut game = Game::with_seed(4, 0);
+        game.palaces = vec![
+            vec![2, 2, 0, 0],
+            vec![4, 4, 8, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+        ];
+        assert_eq!(0, game.last_gain());
+        game.move_palaces(MoveDirection::Left);
+        assert_eq!(12, game.last_gain());
+        assert_eq!(vec![12], game.scores);
+        assert_eq!(Some(&(8, 2)), game.tile_counts().last());
+        assert_eq!(win_tile(4), game.target_tile());
+
+        game.palaces[3][3] = win_tile(4);
+        assert_eq!(win_tile(4) * 2, game.target_tile());
+        assert_eq!(512, Game::with_seed(3, 1).target_tile());
+    }
+
+    #[test]
+    fn test_insert_top_score() {
+        let mut game = Game::with_seed(4, 0);
+        game.total_score = 100;
+        assert!(!game.insert_top_score().unwrap());
+        assert_eq!(100, game.top_score);
+
+        let mut game = Game::with_seed(4, 0);
+        game.top_score = 100;
+        game.total_score = 100;
+        assert!(!game.insert_top_score().unwrap());
+        game.total_score = 200;
+        assert!(game.insert_top_score().unwrap());
+        assert_eq!(200, game.top_score);
+        game.total_score = 300;
+        assert!(!game.insert_top_score().unwrap());
+        assert_eq!(300, game.top_score);
+    }
+
+    #[test]
+    fn test_render_small() {
+        // 太小的格子不画也不会溢出
+        for (width, height) in [(0, 0), (1, 1), (2, 2), (3, 1), (1, 3)] {
+            let area = Rect::new(0, 0, width, height);
+            let mut buf = Buffer::empty(area);
+            Palace::default().num(2048).render(area, &mut buf);
+        }
+        // 放不下的数字截掉，不画出格子
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 3));
+        Palace::default().num(2048).render(area, &mut buf);
+        let row = (0..8)
+            .map(|x| buf.get(x, 1).symbol.as_str())
+            .collect::<String>();
+        assert_eq!("2048    ", row);
+    }
+
+    #[test]
+    fn test_slides() {
+        let mut game = Game::with_seed(4, 0);
+        game.palaces = vec![
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![2, 0, 0, 0],
+            vec![2, 0, 0, 4],
+        ];
+        let moved = game.move_palaces(MoveDirection::Down);
+        let slide = |from, to, num| Slide { from, to, num };
+        assert_eq!(
+            vec![
+                slide((3, 0), (3, 0), 2),
+                slide((2, 0), (3, 0), 2),
+                slide((3, 3), (3, 3), 4),
+            ],
+            moved.slides
+        );
+        assert_eq!(vec![(3, 0)], moved.merged);
+        let (x, y) = moved.spawned.unwrap();
+        assert_ne!(0, game.palaces[x][y]);
+        assert!(moved.changed());
+
+        // 没有数字方块移动时不算一步，也不生成新数字
+        let palaces = vec![vec![2, 4, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]];
+        game.palaces = palaces.clone();
+        let steps = (game.steps.len(), game.move_steps, game.scores.len());
+        let moved = game.move_palaces(MoveDirection::Left);
+        assert!(!moved.changed());
+        assert_eq!(None, moved.spawned);
+        assert_eq!(palaces, game.palaces);
+        assert_eq!(
+            steps,
+            (game.steps.len(), game.move_steps, game.scores.len())
+        );
+    }
+
+    #[test]
+    fn test_hint() {
+        let mut game = Game::with_seed(4, 0);
+        game.palaces = vec![
+            vec![2, 4, 8, 16],
+            vec![2, 0, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+        ];
+        // 向上或向下能合并 2
+        assert!(matches!(
+            game.hint(),
+            Some(MoveDirection::Up | MoveDirection::Down)
+        ));
+        // 只有向下能移动
+        game.palaces[1][0] = 0;
+        assert_eq!(Some(MoveDirection::Down), game.hint());
+        game.palaces = vec![vec![2, 4], vec![4, 2]];
+        assert_eq!(None, game.hint());
+    }
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
     fn test_merge() {
-        let mut game = Game::new();
+        let mut game = Game::with_seed(4, 0);
 
         // 向左
         let mut palaces = vec![
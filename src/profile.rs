@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::config::CONFIG_FILE;
+use crate::i18n;
+use crate::leaderboard::NAME_LEN;
+
+/// 默认档案，数据直接保存在数据目录下，与没有档案时的旧数据兼容
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 访客档案，数据只保存在内存中
+pub const GUEST_PROFILE: &str = "guest";
+
+/// 其它档案所在的子目录
+const PROFILES_DIR: &str = "profiles";
+
+/// 记录上次使用的档案的文件
+const LAST_PROFILE: &str = "profile";
+
+/// 档案的数据目录，访客档案没有数据目录
+pub fn dir(data_dir: &Path, name: &str) -> Option<PathBuf> {
+    match name {
+        DEFAULT_PROFILE => Some(data_dir.to_path_buf()),
+        GUEST_PROFILE => None,
+        _ => Some(data_dir.join(PROFILES_DIR).join(name)),
+    }
+}
+
+/// 档案自己的配置文件，会覆盖全局配置；默认档案直接使用全局配置
+pub fn config_path(data_dir: &Path, name: &str) -> Option<PathBuf> {
+    match name {
+        DEFAULT_PROFILE | GUEST_PROFILE => None,
+        _ => dir(data_dir, name).map(|dir| dir.join(CONFIG_FILE)),
+    }
+}
+
+/// 全部档案：默认档案在最前，访客档案在最后
+pub fn list(data_dir: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(data_dir.join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| validate(name).is_ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.retain(|name| name != DEFAULT_PROFILE && name != GUEST_PROFILE);
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names.push(GUEST_PROFILE.to_string());
+    names
+}
+
+/// 档案名会用作目录名，不能包含路径分隔符
+pub fn validate(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!(i18n::messages().empty_profile_name);
+    }
+    if name.chars().count() > NAME_LEN {
+        bail!("{}{}", i18n::messages().profile_name_too_long, NAME_LEN);
+    }
+    if name.starts_with('.')
+        || name
+            .chars()
+            .any(|c| matches!(c, '/' | '\\') || c.is_control())
+    {
+        bail!("{}`{}`", i18n::messages().invalid_profile_name, name);
+    }
+    Ok(())
+}
+
+/// 上次使用的档案
+pub fn last(data_dir: &Path) -> Option<String> {
+    let name = fs::read_to_string(data_dir.join(LAST_PROFILE)).ok()?;
+    let name = name.trim();
+    validate(name).ok().map(|_| name.to_string())
+}
+
+/// 记住这次使用的档案，访客档案不记录
+pub fn remember(data_dir: &Path, name: &str) -> Result<()> {
+    if name == GUEST_PROFILE {
+        return Ok(());
+    }
+    fs::create_dir_all(data_dir)?;
+    fs::write(data_dir.join(LAST_PROFILE), name)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let data_dir = std::env::temp_dir().join(format!("t-2048-profiles-{}", std::process::id()));
+        assert_eq!(vec![DEFAULT_PROFILE, GUEST_PROFILE], list(&data_dir));
+        assert_eq!(None, last(&data_dir));
+
+        for name in &["bob", "小明"] {
+            validate(name).unwrap();
+            fs::create_dir_all(dir(&data_dir, name).unwrap()).unwrap();
+        }
+        assert_eq!(
+            vec![DEFAULT_PROFILE, "bob", "小明", GUEST_PROFILE],
+            list(&data_dir)
+        );
+        assert_eq!(Some(data_dir.clone()), dir(&data_dir, DEFAULT_PROFILE));
+        assert_eq!(None, dir(&data_dir, GUEST_PROFILE));
+
+        remember(&data_dir, "bob").unwrap();
+        remember(&data_dir, GUEST_PROFILE).unwrap();
+        assert_eq!(Some("bob".to_string()), last(&data_dir));
+
+        for name in &["", " ", "../x", ".hidden", "a/b", "abcdefghijklmnopq"] {
+            assert!(validate(name).is_err(), "{}", name);
+        }
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use tui::style::Color;
+
+/// RGBA 图片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// 按行排列的像素
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// 全透明的图片
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 4]; (width * height) as usize],
+        }
+    }
+
+    /// 按覆盖率 `alpha` 把颜色叠加到像素上
+    fn blend(&mut self, x: u32, y: u32, color: [u8; 3], alpha: f64) {
+        if alpha <= 0.0 {
+            return;
+        }
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let below = pixel[3] as f64 / 255.0;
+        let out = alpha + below * (1.0 - alpha);
+        for i in 0..3 {
+            let mixed = color[i] as f64 * alpha + pixel[i] as f64 * below * (1.0 - alpha);
+            pixel[i] = (mixed / out).round() as u8;
+        }
+        pixel[3] = (out * 255.0).round() as u8;
+    }
+}
+
+/// 数字的笔画，坐标以数字的宽和高为 1，按七段数码管排列，最后一笔为居中的 1
+const SEGMENTS: [[(f64, f64); 2]; 8] = [
+    [(0.0, 0.0), (1.0, 0.0)],
+    [(1.0, 0.0), (1.0, 0.5)],
+    [(1.0, 0.5), (1.0, 1.0)],
+    [(0.0, 1.0), (1.0, 1.0)],
+    [(0.0, 0.5), (0.0, 1.0)],
+    [(0.0, 0.0), (0.0, 0.5)],
+    [(0.0, 0.5), (1.0, 0.5)],
+    [(0.5, 0.0), (0.5, 1.0)],
+];
+
+/// 每个数字用到的笔画，按位对应 `SEGMENTS`
+const DIGITS: [u8; 10] = [
+    0b011_1111,
+    0b1000_0000,
+    0b101_1011,
+    0b100_1111,
+    0b110_0110,
+    0b110_1101,
+    0b111_1101,
+    0b000_0111,
+    0b111_1111,
+    0b110_1111,
+];
+
+/// 数字的宽高比
+const DIGIT_ASPECT: f64 = 0.55;
+
+/// 画一个数字方块：圆角矩形，中间为数字；`num` 为 0 时只画空格子
+pub fn tile(num: u128, color: Color, width: u32, height: u32) -> Image {
+    let mut image = Image::new(width, height);
+    let (w, h) = (width as f64, height as f64);
+    // 四周留出空隙，相邻的方块不会连在一起
+    let margin = (w.min(h) * 0.06).max(1.0);
+    let radius = (w.min(h) - margin * 2.0) * 0.18;
+    let background = rgb(color);
+    for y in 0..height {
+        for x in 0..width {
+            let d = rounded_rect(
+                x as f64 + 0.5,
+                y as f64 + 0.5,
+                (w / 2.0, h / 2.0),
+                (w / 2.0 - margin, h / 2.0 - margin),
+                radius,
+            );
+            image.blend(x, y, background, (0.5 - d).clamp(0.0, 1.0));
+        }
+    }
+    if num == 0 {
+        return image;
+    }
+
+    let text = num.to_string();
+    let count = text.len() as f64;
+    // 数字之间留出数字宽度的 0.4
+    let digit_h =
+        (h * 0.45).min(w * 0.72 / (count * DIGIT_ASPECT + (count - 1.0) * 0.4 * DIGIT_ASPECT));
+    let digit_w = digit_h * DIGIT_ASPECT;
+    let spacing = digit_w * 1.4;
+    let stroke = (digit_h * 0.14).max(1.0);
+    let left = (w - (spacing * (count - 1.0) + digit_w)) / 2.0;
+    let top = (h - digit_h) / 2.0;
+    let segments = text
+        .bytes()
+        .enumerate()
+        .flat_map(|(i, b)| {
+            let mask = DIGITS[(b - b'0') as usize];
+            let x = left + spacing * i as f64;
+            SEGMENTS
+                .iter()
+                .enumerate()
+                .filter(move |(s, _)| mask & (1 << s) != 0)
+                .map(move |(_, &[a, b])| {
+                    let point = |(px, py): (f64, f64)| (x + px * digit_w, top + py * digit_h);
+                    (point(a), point(b))
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let foreground = text_color(background);
+    for y in 0..height {
+        for x in 0..width {
+            let p = (x as f64 + 0.5, y as f64 + 0.5);
+            let d = segments
+                .iter()
+                .map(|&(a, b)| segment(p, a, b))
+                .fold(f64::MAX, f64::min);
+            image.blend(x, y, foreground, (stroke / 2.0 - d + 0.5).clamp(0.0, 1.0));
+        }
+    }
+    image
+}
+
+/// 点到圆角矩形边缘的有符号距离，在内部为负
+fn rounded_rect(x: f64, y: f64, center: (f64, f64), half: (f64, f64), radius: f64) -> f64 {
+    let qx = (x - center.0).abs() - (half.0 - radius);
+    let qy = (y - center.1).abs() - (half.1 - radius);
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+/// 点到线段的距离
+fn segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+    let (apx, apy) = (p.0 - a.0, p.1 - a.1);
+    let t = ((apx * abx + apy * aby) / (abx * abx + aby * aby)).clamp(0.0, 1.0);
+    ((apx - abx * t).powi(2) + (apy - aby * t).powi(2)).sqrt()
+}
+
+/// 浅色背景上用深色数字
+pub fn text_color(background: [u8; 3]) -> [u8; 3] {
+    let [r, g, b] = background;
+    let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+    if luminance > 170.0 {
+        [40, 40, 40]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+/// 终端颜色对应的 RGB，命名颜色取常见终端的默认值
+pub fn rgb(color: Color) -> [u8; 3] {
+    match color {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Black => [0, 0, 0],
+        Color::Red => [205, 49, 49],
+        Color::Green => [13, 188, 121],
+        Color::Yellow => [229, 229, 16],
+        Color::Blue => [36, 114, 200],
+        Color::Magenta => [188, 63, 188],
+        Color::Cyan => [17, 168, 205],
+        Color::Gray => [204, 204, 204],
+        Color::DarkGray => [118, 118, 118],
+        Color::LightRed => [241, 76, 76],
+        Color::LightGreen => [35, 209, 139],
+        Color::LightYellow => [245, 245, 67],
+        Color::LightBlue => [59, 142, 234],
+        Color::LightMagenta => [214, 112, 214],
+        Color::LightCyan => [41, 184, 219],
+        Color::White => [229, 229, 229],
+        Color::Indexed(i) => indexed(i),
+        Color::Reset => [60, 60, 60],
+    }
+}
+
+/// xterm 256 色中 16 以后的颜色
+fn indexed(i: u8) -> [u8; 3] {
+    match i {
+        0..=15 => [128, 128, 128],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = i - 16;
+            [level(i / 36), level(i / 6 % 6), level(i % 6)]
+        }
+        _ => {
+            let v = 8 + (i - 232) * 10;
+            [v, v, v]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    impl Image {
+        fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+            self.pixels[(y * self.width + x) as usize]
+        }
+    }
+
+    #[test]
+    fn test_tile() {
+        let image = tile(8, Color::Rgb(0, 0, 200), 60, 40);
+        assert_eq!(60 * 40, image.pixels.len());
+        // 角上透明，边缘附近为方块的颜色
+        assert_eq!(0, image.pixel(0, 0)[3]);
+        assert_eq!([0, 0, 200, 255], image.pixel(8, 20));
+        // 8 的中间一横
+        assert_eq!([255, 255, 255, 255], image.pixel(30, 20));
+
+        let empty = tile(0, Color::Rgb(0, 0, 200), 60, 40);
+        assert_eq!([0, 0, 200, 255], empty.pixel(30, 20));
+        // 浅色方块用深色数字
+        assert_eq!([40, 40, 40], text_color(rgb(Color::Rgb(255, 230, 200))));
+        assert_eq!([95, 135, 175], indexed(67));
+    }
+}
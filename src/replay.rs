@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::i18n;
+use crate::palace::{Game, MoveDirection};
+
+/// 一步操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    Up,
+    Down,
+    Left,
+    Right,
+    Undo,
+}
+
+impl From<MoveDirection> for Step {
+    fn from(md: MoveDirection) -> Self {
+        match md {
+            MoveDirection::Up => Step::Up,
+            MoveDirection::Down => Step::Down,
+            MoveDirection::Left => Step::Left,
+            MoveDirection::Right => Step::Right,
+        }
+    }
+}
+
+impl Step {
+    /// 移动方向，撤回时为 `None`
+    pub fn direction(self) -> Option<MoveDirection> {
+        match self {
+            Step::Up => Some(MoveDirection::Up),
+            Step::Down => Some(MoveDirection::Down),
+            Step::Left => Some(MoveDirection::Left),
+            Step::Right => Some(MoveDirection::Right),
+            Step::Undo => None,
+        }
+    }
+}
+
+/// 回放：由种子和操作记录可以完整复现一局游戏
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// 宫格大小
+    pub size: usize,
+    /// 随机种子
+    pub seed: u64,
+    /// 可撤回的步数
+    pub undo_limit: usize,
+    /// 操作记录
+    pub steps: Vec<Step>,
+    /// 最终得分
+    pub score: u128,
+    /// 最终宫格
+    pub palaces: Vec<Vec<u128>>,
+    /// 用时（毫秒）
+    #[serde(default)]
+    pub elapsed_ms: u64,
+}
+
+impl Replay {
+    /// 记录一局游戏
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            size: game.palaces.len(),
+            seed: game.seed,
+            undo_limit: config::get().undo_limit,
+            steps: game.steps.clone(),
+            score: game.total_score,
+            palaces: game.palaces.clone(),
+            elapsed_ms: game.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// 读取回放文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let messages = i18n::messages();
+        let content = fs::read(path)
+            .with_context(|| format!("{}{}", messages.read_failed, path.display()))?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("{}{}", messages.malformed, path.display()))
+    }
+
+    /// 重新演算，每一步之后回调一次
+    pub fn play(&self, mut on_step: impl FnMut(usize, Step, &Game)) -> Game {
+        let mut game = Game::with_seed(self.size, self.seed);
+        let mut history: Vec<(Vec<Vec<u128>>, u128, u128)> = Vec::new();
+
+        for (i, &step) in self.steps.iter().enumerate() {
+            match step.direction() {
+                Some(md) => {
+                    let before = (game.palaces.clone(), game.total_score, game.move_steps);
+                    // 与游戏中一致，没有数字方块移动时不记入撤回的历史
+                    if game.move_palaces(md).changed() && self.undo_limit > 0 {
+                        history.push(before);
+                        if history.len() > self.undo_limit {
+                            history.remove(0);
+                        }
+                    }
+                }
+                None => {
+                    if let Some((palaces, total_score, move_steps)) = history.pop() {
+                        game.palaces = palaces;
+                        game.total_score = total_score;
+                        game.move_steps = move_steps;
+                        game.scores.truncate(move_steps as usize);
+                        game.steps.push(Step::Undo);
+                    }
+                }
+            }
+            on_step(i, step, &game);
+        }
+
+        game
+    }
+
+    /// 校验记录的结果与重新演算的结果一致
+    pub fn verify(&self) -> Result<()> {
+        let messages = i18n::messages();
+        let game = self.play(|_, _, _| {});
+        if game.steps != self.steps {
+            bail!(messages.invalid_undo);
+        }
+        if game.total_score != self.score {
+            bail!(
+                "{}{}{}{}",
+                messages.score_mismatch,
+                self.score,
+                messages.recomputed,
+                game.total_score
+            );
+        }
+        if game.palaces != self.palaces {
+            bail!(messages.board_mismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let mut game = Game::with_seed(4, 2048);
+        for &md in &[
+            MoveDirection::Left,
+            MoveDirection::Up,
+            MoveDirection::Right,
+            MoveDirection::Down,
+        ] {
+            game.move_palaces(md);
+        }
+
+        assert_eq!(4, game.move_steps);
+
+        let mut replay = Replay::from_game(&game);
+        replay.undo_limit = 1;
+        assert!(replay.verify().is_ok());
+
+        // 撤回时步数也恢复
+        replay.steps.insert(3, Step::Undo);
+        assert_eq!(3, replay.play(|_, _, _| {}).move_steps);
+        replay.steps.remove(3);
+
+        replay.steps.push(Step::Undo);
+        assert!(replay.verify().is_err());
+        replay.steps.pop();
+
+        replay.score += 4;
+        assert!(replay.verify().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::convert::TryInto;
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+
+use crate::global::{HISTORY, REPLAY, SAVE};
+use crate::i18n;
+
+/// 当前的数据版本
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 数据版本键
+pub const SCHEMA_KEY: &str = "schema_version";
+
+/// 带版本号的记录
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub data: T,
+}
+
+/// 编码为当前版本的记录
+pub fn encode<T: Serialize + ?Sized>(data: &T) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&Envelope {
+        version: SCHEMA_VERSION,
+        data,
+    })?)
+}
+
+/// 解码记录，拒绝比当前程序更新的版本
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let envelope: Envelope<T> = serde_json::from_slice(bytes)?;
+    check_version(envelope.version)?;
+    Ok(envelope.data)
+}
+
+/// 数据版本高于当前程序时无法读取
+pub fn check_version(version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        let messages = i18n::messages();
+        bail!(
+            "{}{} > {}{}",
+            messages.unsupported_version,
+            version,
+            SCHEMA_VERSION,
+            messages.upgrade_hint
+        );
+    }
+    Ok(())
+}
+
+/// 从版本 n 升级到 n + 1
+type Migration = fn(&Db) -> Result<()>;
+
+/// 按顺序排列的 sled 数据升级，下标即起始版本
+const SLED_MIGRATIONS: [Migration; 1] = [sled_v0_to_v1];
+
+/// 打开 sled 数据库时升级到当前版本，返回升级前的版本
+pub fn migrate_sled(db: &Db) -> Result<u32> {
+    let version = match db.get(SCHEMA_KEY)? {
+        Some(version) => String::from_utf8_lossy(&version)
+            .parse::<u32>()
+            .context(i18n::messages().invalid_version)?,
+        // 新建的数据库直接使用当前版本
+        None if db.is_empty() => SCHEMA_VERSION,
+        None => 0,
+    };
+    check_version(version)?;
+
+    for (from, migration) in SLED_MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(db).with_context(|| format!("{}{}", i18n::messages().migration_failed, from))?;
+        db.insert(SCHEMA_KEY, (from + 1).to_string().as_bytes())?;
+    }
+    if db.get(SCHEMA_KEY)?.is_none() {
+        db.insert(SCHEMA_KEY, SCHEMA_VERSION.to_string().as_bytes())?;
+    }
+    db.flush()?;
+    Ok(version)
+}
+
+/// 版本 0 没有版本号：最高分是 16 字节大端序的 u128，其它记录是裸 JSON，
+/// 历史记录可能只有一步
+fn sled_v0_to_v1(db: &Db) -> Result<()> {
+    let wrap = |json: &[u8]| {
+        let mut record = br#"{"version":1,"data":"#.to_vec();
+        record.extend_from_slice(json);
+        record.push(b'}');
+        record
+    };
+
+    for item in db.iter() {
+        let (key, value) = item?;
+        if key.ends_with(b"_top_score") {
+            let score = value
+                .as_ref()
+                .try_into()
+                .map(u128::from_be_bytes)
+                .unwrap_or_default();
+            db.insert(key, wrap(score.to_string().as_bytes()))?;
+        } else if key == HISTORY {
+            if value.first() == Some(&b'[') {
+                db.insert(key, wrap(&value))?;
+            } else {
+                let mut history = b"[".to_vec();
+                history.extend_from_slice(&value);
+                history.push(b']');
+                db.insert(key, wrap(&history))?;
+            }
+        } else if key == REPLAY || key == SAVE {
+            db.insert(key, wrap(&value))?;
+        }
+    }
+    Ok(())
+}
+
+/// JSON 文件的升级，下标即起始版本
+type JsonMigration = fn(&mut serde_json::Value) -> Result<()>;
+
+const JSON_MIGRATIONS: [JsonMigration; 1] = [json_v0_to_v1];
+
+/// 读取 JSON 文件时升级到当前版本
+pub fn migrate_json(data: &mut serde_json::Value) -> Result<u32> {
+    let version = data
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0) as u32;
+    check_version(version)?;
+
+    for (from, migration) in JSON_MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(data)
+            .with_context(|| format!("{}{}", i18n::messages().migration_failed, from))?;
+        data["version"] = (from as u32 + 1).into();
+    }
+    Ok(version)
+}
+
+/// 版本 0 的文件结构与版本 1 相同，只是没有版本号
+fn json_v0_to_v1(_data: &mut serde_json::Value) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::{json_path, sled_path, JsonStorage, SledStorage, Storage};
+
+    /// 把 `tests/fixtures` 下的数据库快照写入临时的 sled 数据库，
+    /// 快照中 `hex` 为二进制值，`text` 为文本值
+    fn sled_fixture(name: &str, fixture: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("t-2048-{}-{}", name, std::process::id()));
+        let db = sled::open(sled_path(&dir)).unwrap();
+        let records: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(fixture).unwrap();
+        for (key, value) in records {
+            let value = match (value.get("hex"), value.get("text")) {
+                (Some(hex), _) => {
+                    let hex = hex.as_str().unwrap();
+                    (0..hex.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                        .collect::<Vec<_>>()
+                }
+                (_, Some(text)) => text.as_str().unwrap().as_bytes().to_vec(),
+                _ => panic!("无效的快照 {}", key),
+            };
+            db.insert(key.as_bytes(), value).unwrap();
+        }
+        db.flush().unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_sled_v0_baseline() {
+        let dir = sled_fixture(
+            "v0-baseline",
+            include_str!("../tests/fixtures/sled_v0_baseline.json"),
+        );
+        {
+            let storage = SledStorage::open(&sled_path(&dir)).unwrap();
+            assert_eq!(2048, storage.top_score(4).unwrap());
+            assert_eq!(512, storage.top_score(3).unwrap());
+            assert_eq!(0, storage.top_score(5).unwrap());
+            let history = storage.histories().unwrap();
+            assert_eq!(1, history.len());
+            assert_eq!(36, history[0].total_score);
+            assert_eq!(Some("1".to_string()), storage.meta(SCHEMA_KEY).unwrap());
+        }
+
+        // 再次打开时不会重复升级
+        let storage = SledStorage::open(&sled_path(&dir)).unwrap();
+        assert_eq!(2048, storage.top_score(4).unwrap());
+        drop(storage);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sled_v0_unversioned() {
+        let dir = sled_fixture(
+            "v0-unversioned",
+            include_str!("../tests/fixtures/sled_v0_unversioned.json"),
+        );
+        let storage = SledStorage::open(&sled_path(&dir)).unwrap();
+        assert_eq!(128, storage.top_score(6).unwrap());
+        assert_eq!(2, storage.histories().unwrap().len());
+        let replay = storage.replay().unwrap().unwrap();
+        assert!(replay.verify().is_ok());
+        assert_eq!(replay.seed, storage.save().unwrap().unwrap().seed);
+        assert!(storage.meta("legacy_imported").unwrap().is_some());
+        drop(storage);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_newer_version() {
+        let dir = sled_fixture("newer", r#"{"schema_version": {"text": "99"}}"#);
+        assert!(SledStorage::open(&sled_path(&dir)).is_err());
+        assert!(decode::<u128>(br#"{"version":99,"data":1}"#).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_json_v0() {
+        let dir = std::env::temp_dir().join(format!("t-2048-json-v0-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            json_path(&dir),
+            include_str!("../tests/fixtures/json_v0.json"),
+        )
+        .unwrap();
+
+        let storage = JsonStorage::open(&json_path(&dir)).unwrap();
+        assert_eq!(1024, storage.top_score(4).unwrap());
+        assert_eq!(1, storage.histories().unwrap().len());
+        storage.flush().unwrap();
+        let content = std::fs::read_to_string(json_path(&dir)).unwrap();
+        assert!(content.contains(r#""version": 1"#));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::palace::Game;
+
+/// 各尺寸达到这个数字算作获胜：3 * 3 为 512，其余为 2048
+pub const fn win_tile(palace_size: usize) -> u128 {
+    match palace_size {
+        3 => 512,
+        _ => 2048,
+    }
+}
+
+/// 一个模式的统计，每局结束时记录
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// 局数
+    pub games: u64,
+    /// 达到 2048 的局数
+    pub wins: u64,
+    /// 每局得分，用于计算平均分和中位数
+    pub scores: Vec<u128>,
+    /// 单局最多步数
+    pub longest_game: u64,
+    /// 总步数
+    pub total_moves: u64,
+    /// 达到过的最大数字
+    pub highest_tile: u128,
+    /// 每局最大数字的分布，键为数字
+    pub max_tiles: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    /// 记录一局游戏
+    pub fn record(&mut self, game: &Game) {
+        let moves = game.moves();
+        let max_tile = game.max_tile();
+
+        self.games += 1;
+        if max_tile >= win_tile(game.palaces.len()) {
+            self.wins += 1;
+        }
+        self.scores.push(game.total_score);
+        self.longest_game = self.longest_game.max(moves);
+        self.total_moves += moves;
+        self.highest_tile = self.highest_tile.max(max_tile);
+        *self.max_tiles.entry(max_tile.to_string()).or_default() += 1;
+    }
+
+    /// 合并另一处记录的统计，局数、步数和分布相加，最大值取较大的
+    pub fn merge(&mut self, other: &Stats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.scores.extend_from_slice(&other.scores);
+        self.longest_game = self.longest_game.max(other.longest_game);
+        self.total_moves += other.total_moves;
+        self.highest_tile = self.highest_tile.max(other.highest_tile);
+        for (tile, &count) in &other.max_tiles {
+            *self.max_tiles.entry(tile.clone()).or_default() += count;
+        }
+    }
+
+    /// 在 `base` 之后记录的局，`self` 须是在 `base` 的基础上继续记录的。
+    /// 最长的一局和最大数字无法还原，保留原值
+    pub fn since(&self, base: &Stats) -> Stats {
+        let mut max_tiles = self.max_tiles.clone();
+        for (tile, count) in &base.max_tiles {
+            if let Some(local) = max_tiles.get_mut(tile) {
+                *local = local.saturating_sub(*count);
+            }
+        }
+        max_tiles.retain(|_, count| *count > 0);
+        Stats {
+            games: self.games.saturating_sub(base.games),
+            wins: self.wins.saturating_sub(base.wins),
+            scores: self
+                .scores
+                .get(base.scores.len()..)
+                .unwrap_or_default()
+                .to_vec(),
+            longest_game: self.longest_game,
+            total_moves: self.total_moves.saturating_sub(base.total_moves),
+            highest_tile: self.highest_tile,
+            max_tiles,
+        }
+    }
+
+    /// 胜率（百分比）
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        self.wins as f64 * 100.0 / self.games as f64
+    }
+
+    /// 平均分
+    pub fn average_score(&self) -> u128 {
+        if self.scores.is_empty() {
+            return 0;
+        }
+        self.scores.iter().sum::<u128>() / self.scores.len() as u128
+    }
+
+    /// 得分中位数
+    pub fn median_score(&self) -> u128 {
+        let mut scores = self.scores.clone();
+        scores.sort_unstable();
+        match scores.len() {
+            0 => 0,
+            n if n % 2 == 0 => (scores[n / 2 - 1] + scores[n / 2]) / 2,
+            n => scores[n / 2],
+        }
+    }
+
+    /// 按数字大小排列的最大数字分布
+    pub fn max_tiles(&self) -> Vec<(u128, u64)> {
+        let mut tiles = self
+            .max_tiles
+            .iter()
+            .filter_map(|(tile, &count)| Some((tile.parse().ok()?, count)))
+            .collect::<Vec<_>>();
+        tiles.sort_unstable();
+        tiles
+    }
+}
+
+/// `stats --json` 输出的一个模式
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub size: usize,
+    pub top_score: u128,
+    pub games: u64,
+    pub wins: u64,
+    pub win_rate: f64,
+    pub average_score: u128,
+    pub median_score: u128,
+    pub longest_game: u64,
+    pub total_moves: u64,
+    pub highest_tile: u128,
+    pub max_tiles: BTreeMap<String, u64>,
+}
+
+impl Summary {
+    pub fn new(size: usize, top_score: u128, stats: &Stats) -> Self {
+        Self {
+            size,
+            top_score,
+            games: stats.games,
+            wins: stats.wins,
+            win_rate: stats.win_rate(),
+            average_score: stats.average_score(),
+            median_score: stats.median_score(),
+            longest_game: stats.longest_game,
+            total_moves: stats.total_moves,
+            highest_tile: stats.highest_tile,
+            max_tiles: stats.max_tiles.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::palace::MoveDirection;
+
+    #[test]
+    fn test_record() {
+        let mut stats = Stats::default();
+        for &(seed, score) in &[(1, 100), (2, 300), (3, 200), (4, 1000)] {
+            let mut game = Game::with_seed(4, seed);
+            game.move_palaces(MoveDirection::Left);
+            game.total_score = score;
+            stats.record(&game);
+        }
+        let mut game = Game::with_seed(4, 5);
+        game.palaces[0][0] = win_tile(4);
+        stats.record(&game);
+
+        assert_eq!(5, stats.games);
+        assert_eq!(1, stats.wins);
+        assert_eq!(20.0, stats.win_rate());
+        assert_eq!(1600 / 5, stats.average_score());
+        assert_eq!(200, stats.median_score());
+        assert_eq!(4, stats.total_moves);
+        assert_eq!(win_tile(4), stats.highest_tile);
+        assert_eq!(Some(&(win_tile(4), 1)), stats.max_tiles().last());
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(stats, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut stats = Stats::default();
+        let mut other = Stats::default();
+        let mut game = Game::with_seed(4, 1);
+        game.total_score = 100;
+        stats.record(&game);
+        game.palaces[0][0] = win_tile(4);
+        game.total_score = 300;
+        other.record(&game);
+        other.record(&game);
+
+        let base = stats.clone();
+        stats.merge(&other);
+        assert_eq!(other, stats.since(&base));
+        assert_eq!(3, stats.games);
+        assert_eq!(2, stats.wins);
+        assert_eq!(vec![100, 300, 300], stats.scores);
+        assert_eq!(win_tile(4), stats.highest_tile);
+        assert_eq!(3, stats.max_tiles.values().sum::<u64>());
+        assert_eq!(Some(&(win_tile(4), 2)), stats.max_tiles().last());
+    }
+
+    #[test]
+    fn test_win_tile() {
+        let mut stats = Stats::default();
+        let mut game = Game::with_seed(3, 1);
+        game.palaces[0][0] = win_tile(3);
+        stats.record(&game);
+        let mut game = Game::with_seed(4, 1);
+        game.palaces[0][0] = win_tile(3);
+        stats.record(&game);
+
+        assert_eq!(512, win_tile(3));
+        assert_eq!(2048, win_tile(5));
+        assert_eq!(2, stats.games);
+        assert_eq!(1, stats.wins);
+    }
+}
@@ -0,0 +1,811 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+
+use crate::achievement::Achievements;
+use crate::archive::Archive;
+use crate::global::{ACHIEVEMENTS, HISTORY, REPLAY, SAVE};
+use crate::i18n;
+use crate::leaderboard::Entry;
+use crate::palace::Game;
+use crate::replay::Replay;
+use crate::schema::{self, SCHEMA_VERSION};
+use crate::stats::Stats;
+
+/// 数据存储后端
+pub trait Storage: Send + Sync {
+    /// 指定模式的最高分
+    fn top_score(&self, palace_size: usize) -> Result<u128>;
+    /// 更新指定模式的最高分
+    fn insert_top_score(&self, palace_size: usize, score: u128) -> Result<()>;
+    /// 指定模式的统计
+    fn stats(&self, palace_size: usize) -> Result<Stats>;
+    /// 更新指定模式的统计
+    fn insert_stats(&self, palace_size: usize, stats: &Stats) -> Result<()>;
+    /// 指定模式的排行榜，得分从高到低
+    fn leaderboard(&self, palace_size: usize) -> Result<Vec<Entry>>;
+    /// 覆盖指定模式的排行榜
+    fn insert_leaderboard(&self, palace_size: usize, entries: &[Entry]) -> Result<()>;
+    /// 成就进度
+    fn achievements(&self) -> Result<Achievements>;
+    /// 更新成就进度
+    fn insert_achievements(&self, achievements: &Achievements) -> Result<()>;
+    /// 撤回用的历史记录，最近的一步在最后
+    fn histories(&self) -> Result<Vec<Game>>;
+    /// 覆盖历史记录，为空时删除
+    fn insert_histories(&self, history: &[&Game]) -> Result<()>;
+    /// 未完成的存档
+    fn save(&self) -> Result<Option<Replay>>;
+    /// 保存存档
+    fn insert_save(&self, save: &Replay) -> Result<()>;
+    /// 删除存档
+    fn remove_save(&self) -> Result<()>;
+    /// 最近一局的回放
+    fn replay(&self) -> Result<Option<Replay>>;
+    /// 保存最近一局的回放
+    fn insert_replay(&self, replay: &Replay) -> Result<()>;
+    /// 其它元数据
+    fn meta(&self, key: &str) -> Result<Option<String>>;
+    /// 保存元数据
+    fn insert_meta(&self, key: &str, value: &str) -> Result<()>;
+    /// 写入磁盘
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+    /// 数据库被其它实例占用，数据暂时只保存在内存中
+    fn is_locked(&self) -> bool {
+        false
+    }
+}
+
+/// 存储后端类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageKind {
+    /// sled 数据库
+    Sled,
+    /// 单个 JSON 文件，便于查看和同步
+    Json,
+    /// 仅保存在内存中，退出后丢失
+    Memory,
+}
+
+impl FromStr for StorageKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sled" => Ok(StorageKind::Sled),
+            "json" => Ok(StorageKind::Json),
+            "memory" => Ok(StorageKind::Memory),
+            _ => {
+                let messages = i18n::messages();
+                bail!(
+                    "{}`{}`{}sled, json, memory",
+                    messages.unknown_storage,
+                    s,
+                    messages.choices
+                )
+            }
+        }
+    }
+}
+
+/// 打开数据目录下指定类型的存储
+pub fn open(kind: StorageKind, data_dir: &Path) -> Result<Box<dyn Storage>> {
+    Ok(match kind {
+        StorageKind::Sled => match SledStorage::open(&sled_path(data_dir)) {
+            Err(e) if is_locked(&e) => Box::new(LockedStorage::new(&sled_path(data_dir))),
+            storage => Box::new(storage?),
+        },
+        StorageKind::Json => Box::new(JsonStorage::open(&json_path(data_dir))?),
+        StorageKind::Memory => Box::new(MemoryStorage::default()),
+    })
+}
+
+/// sled 数据库位置
+pub fn sled_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("T2048")
+}
+
+/// JSON 文件位置
+pub fn json_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("t-2048.json")
+}
+
+/// 打开 sled 数据库失败是否因为另一个进程持有锁
+fn is_locked(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| match e.downcast_ref::<sled::Error>() {
+        Some(sled::Error::Io(io)) => io.to_string().contains("could not acquire lock"),
+        _ => false,
+    })
+}
+
+/// sled 数据库
+pub struct SledStorage {
+    db: Db,
+}
+
+/// 同一进程关闭数据库后，sled 的后台线程会再持有锁一小段时间，
+/// 重新打开时在这段时间内重试
+const REOPEN_WAIT: Duration = Duration::from_millis(500);
+
+impl SledStorage {
+    /// 打开数据库，旧版本的数据会先升级到当前版本
+    pub fn open(path: &Path) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            match Self::try_open(path) {
+                Err(e) if is_locked(&e) && start.elapsed() < REOPEN_WAIT => {
+                    std::thread::sleep(Duration::from_millis(20))
+                }
+                storage => return storage,
+            }
+        }
+    }
+
+    /// 打开数据库，被占用时不等待
+    fn try_open(path: &Path) -> Result<Self> {
+        let messages = i18n::messages();
+        let db = sled::open(path)
+            .with_context(|| format!("{}{}", messages.open_db_failed, path.display()))?;
+        schema::migrate_sled(&db)
+            .with_context(|| format!("{}{}", messages.upgrade_failed, path.display()))?;
+        Ok(Self { db })
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.db.get(key)? {
+            Some(value) => Ok(Some(schema::decode(&value).with_context(|| {
+                format!("{}`{}`", i18n::messages().invalid_record, key)
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    fn insert_json<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<()> {
+        self.db.insert(key, schema::encode(value)?)?;
+        Ok(())
+    }
+}
+
+impl Storage for SledStorage {
+    fn top_score(&self, palace_size: usize) -> Result<u128> {
+        Ok(self
+            .get_json(&format!("{}_top_score", palace_size))?
+            .unwrap_or(0))
+    }
+
+    fn insert_top_score(&self, palace_size: usize, score: u128) -> Result<()> {
+        self.insert_json(&format!("{}_top_score", palace_size), &score)
+    }
+
+    fn stats(&self, palace_size: usize) -> Result<Stats> {
+        Ok(self
+            .get_json(&format!("{}_stats", palace_size))?
+            .unwrap_or_default())
+    }
+
+    fn insert_stats(&self, palace_size: usize, stats: &Stats) -> Result<()> {
+        self.insert_json(&format!("{}_stats", palace_size), stats)
+    }
+
+    fn leaderboard(&self, palace_size: usize) -> Result<Vec<Entry>> {
+        Ok(self
+            .get_json(&format!("{}_leaderboard", palace_size))?
+            .unwrap_or_default())
+    }
+
+    fn insert_leaderboard(&self, palace_size: usize, entries: &[Entry]) -> Result<()> {
+        self.insert_json(&format!("{}_leaderboard", palace_size), entries)
+    }
+
+    fn achievements(&self) -> Result<Achievements> {
+        Ok(self.get_json(ACHIEVEMENTS)?.unwrap_or_default())
+    }
+
+    fn insert_achievements(&self, achievements: &Achievements) -> Result<()> {
+        self.insert_json(ACHIEVEMENTS, achievements)
+    }
+
+    fn histories(&self) -> Result<Vec<Game>> {
+        Ok(self.get_json(HISTORY)?.unwrap_or_default())
+    }
+
+    fn insert_histories(&self, history: &[&Game]) -> Result<()> {
+        if history.is_empty() {
+            self.db.remove(HISTORY)?;
+            return Ok(());
+        }
+        self.insert_json(HISTORY, history)
+    }
+
+    fn save(&self) -> Result<Option<Replay>> {
+        self.get_json(SAVE)
+    }
+
+    fn insert_save(&self, save: &Replay) -> Result<()> {
+        self.insert_json(SAVE, save)
+    }
+
+    fn remove_save(&self) -> Result<()> {
+        self.db.remove(SAVE)?;
+        Ok(())
+    }
+
+    fn replay(&self) -> Result<Option<Replay>> {
+        self.get_json(REPLAY)
+    }
+
+    fn insert_replay(&self, replay: &Replay) -> Result<()> {
+        self.insert_json(REPLAY, replay)
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        match self.db.get(key)? {
+            Some(value) => Ok(Some(String::from_utf8_lossy(&value).into_owned())),
+            None => Ok(None),
+        }
+    }
+
+    fn insert_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.db.insert(key, value.as_bytes())?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// 全部数据，供内存和 JSON 文件存储使用
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Data {
+    version: u32,
+    top_scores: BTreeMap<usize, u128>,
+    stats: BTreeMap<usize, Stats>,
+    leaderboards: BTreeMap<usize, Vec<Entry>>,
+    achievements: Achievements,
+    history: Vec<Game>,
+    save: Option<Replay>,
+    replay: Option<Replay>,
+    meta: BTreeMap<String, String>,
+}
+
+/// 仅保存在内存中
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: Mutex<Data>,
+}
+
+impl MemoryStorage {
+    fn read<T>(&self, f: impl FnOnce(&Data) -> T) -> T {
+        f(&self.data.lock().unwrap())
+    }
+
+    fn write(&self, f: impl FnOnce(&mut Data)) {
+        f(&mut self.data.lock().unwrap())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn top_score(&self, palace_size: usize) -> Result<u128> {
+        Ok(self.read(|data| data.top_scores.get(&palace_size).copied().unwrap_or(0)))
+    }
+
+    fn insert_top_score(&self, palace_size: usize, score: u128) -> Result<()> {
+        self.write(|data| {
+            data.top_scores.insert(palace_size, score);
+        });
+        Ok(())
+    }
+
+    fn stats(&self, palace_size: usize) -> Result<Stats> {
+        Ok(self.read(|data| data.stats.get(&palace_size).cloned().unwrap_or_default()))
+    }
+
+    fn insert_stats(&self, palace_size: usize, stats: &Stats) -> Result<()> {
+        self.write(|data| {
+            data.stats.insert(palace_size, stats.clone());
+        });
+        Ok(())
+    }
+
+    fn leaderboard(&self, palace_size: usize) -> Result<Vec<Entry>> {
+        Ok(self.read(|data| {
+            data.leaderboards
+                .get(&palace_size)
+                .cloned()
+                .unwrap_or_default()
+        }))
+    }
+
+    fn insert_leaderboard(&self, palace_size: usize, entries: &[Entry]) -> Result<()> {
+        self.write(|data| {
+            data.leaderboards.insert(palace_size, entries.to_vec());
+        });
+        Ok(())
+    }
+
+    fn achievements(&self) -> Result<Achievements> {
+        Ok(self.read(|data| data.achievements.clone()))
+    }
+
+    fn insert_achievements(&self, achievements: &Achievements) -> Result<()> {
+        self.write(|data| data.achievements = achievements.clone());
+        Ok(())
+    }
+
+    fn histories(&self) -> Result<Vec<Game>> {
+        Ok(self.read(|data| data.history.clone()))
+    }
+
+    fn insert_histories(&self, history: &[&Game]) -> Result<()> {
+        self.write(|data| data.history = history.iter().map(|&game| game.clone()).collect());
+        Ok(())
+    }
+
+    fn save(&self) -> Result<Option<Replay>> {
+        Ok(self.read(|data| data.save.clone()))
+    }
+
+    fn insert_save(&self, save: &Replay) -> Result<()> {
+        self.write(|data| data.save = Some(save.clone()));
+        Ok(())
+    }
+
+    fn remove_save(&self) -> Result<()> {
+        self.write(|data| data.save = None);
+        Ok(())
+    }
+
+    fn replay(&self) -> Result<Option<Replay>> {
+        Ok(self.read(|data| data.replay.clone()))
+    }
+
+    fn insert_replay(&self, replay: &Replay) -> Result<()> {
+        self.write(|data| data.replay = Some(replay.clone()));
+        Ok(())
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.read(|data| data.meta.get(key).cloned()))
+    }
+
+    fn insert_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.write(|data| {
+            data.meta.insert(key.to_string(), value.to_string());
+        });
+        Ok(())
+    }
+}
+
+/// 单个 JSON 文件，每次修改后整体写回
+pub struct JsonStorage {
+    path: PathBuf,
+    memory: MemoryStorage,
+}
+
+impl JsonStorage {
+    /// 读取文件，旧版本的数据会先升级到当前版本并写回
+    pub fn open(path: &Path) -> Result<Self> {
+        let (data, migrated) = if path.exists() {
+            let messages = i18n::messages();
+            let content = fs::read(path)
+                .with_context(|| format!("{}{}", messages.read_failed, path.display()))?;
+            let mut value = serde_json::from_slice(&content)
+                .with_context(|| format!("{}{}", messages.malformed, path.display()))?;
+            let version = schema::migrate_json(&mut value)
+                .with_context(|| format!("{}{}", messages.upgrade_failed, path.display()))?;
+            let data = serde_json::from_value(value)
+                .with_context(|| format!("{}{}", messages.malformed, path.display()))?;
+            (data, version < SCHEMA_VERSION)
+        } else {
+            (Data::default(), false)
+        };
+
+        let storage = Self {
+            path: path.to_path_buf(),
+            memory: MemoryStorage {
+                data: Mutex::new(data),
+            },
+        };
+        if migrated {
+            storage.write_back()?;
+        }
+        Ok(storage)
+    }
+
+    /// 先写入临时文件再替换，避免写到一半时文件损坏
+    fn write_back(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.memory.write(|data| data.version = SCHEMA_VERSION);
+        let json = self.memory.read(serde_json::to_vec_pretty)?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("{}{}", i18n::messages().write_failed, self.path.display()))?;
+        Ok(())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn top_score(&self, palace_size: usize) -> Result<u128> {
+        self.memory.top_score(palace_size)
+    }
+
+    fn insert_top_score(&self, palace_size: usize, score: u128) -> Result<()> {
+        self.memory.insert_top_score(palace_size, score)?;
+        self.write_back()
+    }
+
+    fn stats(&self, palace_size: usize) -> Result<Stats> {
+        self.memory.stats(palace_size)
+    }
+
+    fn insert_stats(&self, palace_size: usize, stats: &Stats) -> Result<()> {
+        self.memory.insert_stats(palace_size, stats)?;
+        self.write_back()
+    }
+
+    fn leaderboard(&self, palace_size: usize) -> Result<Vec<Entry>> {
+        self.memory.leaderboard(palace_size)
+    }
+
+    fn insert_leaderboard(&self, palace_size: usize, entries: &[Entry]) -> Result<()> {
+        self.memory.insert_leaderboard(palace_size, entries)?;
+        self.write_back()
+    }
+
+    fn achievements(&self) -> Result<Achievements> {
+        self.memory.achievements()
+    }
+
+    fn insert_achievements(&self, achievements: &Achievements) -> Result<()> {
+        self.memory.insert_achievements(achievements)?;
+        self.write_back()
+    }
+
+    fn histories(&self) -> Result<Vec<Game>> {
+        self.memory.histories()
+    }
+
+    fn insert_histories(&self, history: &[&Game]) -> Result<()> {
+        self.memory.insert_histories(history)?;
+        self.write_back()
+    }
+
+    fn save(&self) -> Result<Option<Replay>> {
+        self.memory.save()
+    }
+
+    fn insert_save(&self, save: &Replay) -> Result<()> {
+        self.memory.insert_save(save)?;
+        self.write_back()
+    }
+
+    fn remove_save(&self) -> Result<()> {
+        self.memory.remove_save()?;
+        self.write_back()
+    }
+
+    fn replay(&self) -> Result<Option<Replay>> {
+        self.memory.replay()
+    }
+
+    fn insert_replay(&self, replay: &Replay) -> Result<()> {
+        self.memory.insert_replay(replay)?;
+        self.write_back()
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        self.memory.meta(key)
+    }
+
+    fn insert_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.memory.insert_meta(key, value)?;
+        self.write_back()
+    }
+}
+
+/// 复制数据库并读出全部数据，旧版本的数据只在副本上升级，原来的文件不会改动。
+/// sled 的锁只针对原来的文件，被占用的数据库也可以这样读取
+pub fn snapshot(path: &Path) -> Result<Archive> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let copy = std::env::temp_dir().join(format!(
+        "t-2048-snapshot-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let archive =
+        copy_dir(path, &copy).and_then(|_| Archive::from_storage(&SledStorage::try_open(&copy)?));
+    let _ = fs::remove_dir_all(&copy);
+    archive
+}
+
+/// 递归复制目录
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// 重新尝试打开被占用的数据库的间隔
+const LOCK_RETRY: Duration = Duration::from_secs(5);
+
+/// 数据库被另一个实例占用时使用：内存中先放入数据库的只读快照，之后的数据保存在内存中，
+/// 数据库解锁后合并回去（最高分取较大值，统计加上快照之后记录的局），之后直接读写数据库
+pub struct LockedStorage {
+    path: PathBuf,
+    memory: MemoryStorage,
+    /// 快照中的统计，合并时只加上之后记录的部分
+    base: BTreeMap<usize, Stats>,
+    disk: Mutex<Option<SledStorage>>,
+    retried: Mutex<Instant>,
+}
+
+impl LockedStorage {
+    pub fn new(path: &Path) -> Self {
+        let memory = MemoryStorage::default();
+        // 读不到快照时从空白开始，不影响游戏
+        let base = match snapshot(path) {
+            Ok(archive) if archive.import_into(&memory, true).is_ok() => archive.stats,
+            _ => BTreeMap::new(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            memory,
+            base,
+            disk: Mutex::new(None),
+            retried: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 尝试打开数据库并合并内存中的数据，返回数据库是否可用
+    pub fn reconcile(&self) -> Result<bool> {
+        let mut disk = self.disk.lock().unwrap();
+        if disk.is_some() {
+            return Ok(true);
+        }
+        *self.retried.lock().unwrap() = Instant::now();
+        let storage = match SledStorage::try_open(&self.path) {
+            Ok(storage) => storage,
+            Err(e) if is_locked(&e) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        // 统计在两处各自记录了不同的局，只合并快照之后记录的局
+        let mut archive = Archive::from_storage(&self.memory)?;
+        for (size, stats) in archive.stats.iter_mut() {
+            *stats = stats.since(&self.base.get(size).cloned().unwrap_or_default());
+        }
+        archive.import_into(&storage, false)?;
+        *disk = Some(storage);
+        Ok(true)
+    }
+
+    /// 写入前每隔一段时间重新尝试打开数据库
+    fn retry(&self) -> Result<()> {
+        if self.retried.lock().unwrap().elapsed() >= LOCK_RETRY {
+            self.reconcile()?;
+        }
+        Ok(())
+    }
+
+    /// 数据库可用时读写数据库，否则读写内存
+    fn with<T>(&self, f: impl FnOnce(&dyn Storage) -> Result<T>) -> Result<T> {
+        match &*self.disk.lock().unwrap() {
+            Some(disk) => f(disk),
+            None => f(&self.memory),
+        }
+    }
+}
+
+impl Storage for LockedStorage {
+    fn top_score(&self, palace_size: usize) -> Result<u128> {
+        self.with(|s| s.top_score(palace_size))
+    }
+
+    fn insert_top_score(&self, palace_size: usize, score: u128) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_top_score(palace_size, score))
+    }
+
+    fn stats(&self, palace_size: usize) -> Result<Stats> {
+        self.with(|s| s.stats(palace_size))
+    }
+
+    fn insert_stats(&self, palace_size: usize, stats: &Stats) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_stats(palace_size, stats))
+    }
+
+    fn leaderboard(&self, palace_size: usize) -> Result<Vec<Entry>> {
+        self.with(|s| s.leaderboard(palace_size))
+    }
+
+    fn insert_leaderboard(&self, palace_size: usize, entries: &[Entry]) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_leaderboard(palace_size, entries))
+    }
+
+    fn achievements(&self) -> Result<Achievements> {
+        self.with(|s| s.achievements())
+    }
+
+    fn insert_achievements(&self, achievements: &Achievements) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_achievements(achievements))
+    }
+
+    fn histories(&self) -> Result<Vec<Game>> {
+        self.with(|s| s.histories())
+    }
+
+    fn insert_histories(&self, history: &[&Game]) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_histories(history))
+    }
+
+    fn save(&self) -> Result<Option<Replay>> {
+        self.with(|s| s.save())
+    }
+
+    fn insert_save(&self, save: &Replay) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_save(save))
+    }
+
+    fn remove_save(&self) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.remove_save())
+    }
+
+    fn replay(&self) -> Result<Option<Replay>> {
+        self.with(|s| s.replay())
+    }
+
+    fn insert_replay(&self, replay: &Replay) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_replay(replay))
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        self.with(|s| s.meta(key))
+    }
+
+    fn insert_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.retry()?;
+        self.with(|s| s.insert_meta(key, value))
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.reconcile()?;
+        self.with(|s| s.flush())
+    }
+
+    fn is_locked(&self) -> bool {
+        self.disk.lock().unwrap().is_none()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::achievement::Event;
+
+    fn check(storage: &dyn Storage) {
+        assert_eq!(0, storage.top_score(4).unwrap());
+        storage.insert_top_score(4, 2048).unwrap();
+        assert_eq!(2048, storage.top_score(4).unwrap());
+        assert_eq!(0, storage.top_score(5).unwrap());
+
+        let mut stats = Stats::default();
+        stats.record(&Game::with_seed(4, 1));
+        storage.insert_stats(4, &stats).unwrap();
+        assert_eq!(stats, storage.stats(4).unwrap());
+        assert_eq!(Stats::default(), storage.stats(5).unwrap());
+
+        let entries = vec![Entry::from_game(&Game::with_seed(4, 1))];
+        storage.insert_leaderboard(4, &entries).unwrap();
+        assert_eq!(entries, storage.leaderboard(4).unwrap());
+        assert!(storage.leaderboard(5).unwrap().is_empty());
+
+        let mut achievements = Achievements::default();
+        achievements.update(&Event::Finished);
+        storage.insert_achievements(&achievements).unwrap();
+        assert_eq!(achievements, storage.achievements().unwrap());
+
+        let game = Game::with_seed(4, 1);
+        storage.insert_histories(&[&game, &game]).unwrap();
+        assert_eq!(2, storage.histories().unwrap().len());
+        storage.insert_histories(&[]).unwrap();
+        assert!(storage.histories().unwrap().is_empty());
+
+        let replay = Replay::from_game(&game);
+        assert!(storage.save().unwrap().is_none());
+        storage.insert_save(&replay).unwrap();
+        assert_eq!(game.palaces, storage.save().unwrap().unwrap().palaces);
+        storage.remove_save().unwrap();
+        assert!(storage.save().unwrap().is_none());
+
+        storage.insert_replay(&replay).unwrap();
+        assert_eq!(replay.seed, storage.replay().unwrap().unwrap().seed);
+
+        storage.insert_meta("key", "value").unwrap();
+        assert_eq!(Some("value".to_string()), storage.meta("key").unwrap());
+        storage.flush().unwrap();
+    }
+
+    #[test]
+    fn test_storages() {
+        let dir = std::env::temp_dir().join(format!("t-2048-storage-{}", std::process::id()));
+
+        for &kind in &[StorageKind::Memory, StorageKind::Sled, StorageKind::Json] {
+            check(open(kind, &dir).unwrap().as_ref());
+        }
+
+        let json = JsonStorage::open(&json_path(&dir)).unwrap();
+        assert_eq!(2048, json.top_score(4).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_locked() {
+        let dir = std::env::temp_dir().join(format!("t-2048-locked-{}", std::process::id()));
+        let first = open(StorageKind::Sled, &dir).unwrap();
+        first.insert_top_score(4, 1000).unwrap();
+        let mut stats = Stats::default();
+        stats.record(&Game::with_seed(4, 1));
+        first.insert_stats(4, &stats).unwrap();
+        first.flush().unwrap();
+
+        let second = open(StorageKind::Sled, &dir).unwrap();
+        assert!(second.is_locked());
+        // 被占用时读到的是数据库的快照
+        assert_eq!(1000, second.top_score(4).unwrap());
+        let mut stats = second.stats(4).unwrap();
+        assert_eq!(1, stats.games);
+        stats.record(&Game::with_seed(4, 2));
+        second.insert_top_score(4, 2048).unwrap();
+        second.insert_top_score(5, 100).unwrap();
+        second.insert_stats(4, &stats).unwrap();
+        second.flush().unwrap();
+        assert!(second.is_locked());
+        assert_eq!(1000, first.top_score(4).unwrap());
+
+        drop(first);
+        second.flush().unwrap();
+        assert!(!second.is_locked());
+        assert_eq!(2048, second.top_score(4).unwrap());
+        assert_eq!(100, second.top_score(5).unwrap());
+        assert_eq!(2, second.stats(4).unwrap().games);
+        drop(second);
+
+        let storage = SledStorage::open(&sled_path(&dir)).unwrap();
+        assert_eq!(2048, storage.top_score(4).unwrap());
+        drop(storage);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
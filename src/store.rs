@@ -1,84 +1,312 @@
-use crate::global::{HISTORY, PALACE_SIZE};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::achievement::{Achievement, Achievements, Event};
+use crate::archive::{Archive, ImportReport};
+use crate::config;
+use crate::global::{LEGACY_IMPORTED, PALACE_SIZE, PLAYER_NAME};
+use crate::leaderboard::{self, Entry};
 use crate::palace::Game;
+use crate::profile::DEFAULT_PROFILE;
+use crate::replay::Replay;
+use crate::stats::Stats;
+use crate::storage::{self, Storage};
 use anyhow::Result;
-use sled::{self, Db};
 
-lazy_static! {
-    static ref DB: Db = sled::open(".T2048/T2048").unwrap();
+/// 当前档案的存储，切换档案时替换
+static STORAGE: RwLock<Option<Arc<dyn Storage>>> = RwLock::new(None);
+
+/// 全局存储，未初始化时按配置打开
+fn storage() -> Arc<dyn Storage> {
+    if let Some(storage) = &*STORAGE.read().unwrap() {
+        return Arc::clone(storage);
+    }
+    let storage: Arc<dyn Storage> = Arc::from(open().expect("无法打开存储"));
+    *STORAGE.write().unwrap() = Some(Arc::clone(&storage));
+    storage
+}
+
+/// 打开当前档案的存储，访客档案只保存在内存中
+#[cfg(not(test))]
+fn open() -> Result<Box<dyn Storage>> {
+    let config = config::get();
+    match crate::profile::dir(&config.data_dir, &config.profile) {
+        Some(dir) => storage::open(config.storage, &dir),
+        None => Ok(Box::new(storage::MemoryStorage::default())),
+    }
 }
 
-///
+/// 测试时只使用内存存储
+#[cfg(test)]
+fn open() -> Result<Box<dyn Storage>> {
+    Ok(Box::new(storage::MemoryStorage::default()))
+}
+
+/// 旧版本的数据库位置，相对于启动游戏时的当前目录
+pub const LEGACY_DB: &str = ".T2048/T2048";
+
+/// 数据存储
 pub struct Store;
 impl Store {
+    /// 打开存储，在进入终端界面之前调用以便错误能正常输出
+    /// 切换档案后再次调用会换成新档案的存储
+    pub fn init() -> Result<()> {
+        let storage = open()?;
+        *STORAGE.write().unwrap() = Some(Arc::from(storage));
+        Ok(())
+    }
+
+    /// 数据库被另一个实例占用，数据暂存在内存中
+    pub fn is_locked() -> bool {
+        storage().is_locked()
+    }
+
+    /// 导入当前目录下旧版本的数据库，只会导入一次
+    pub fn import_legacy() -> Result<Option<PathBuf>> {
+        let legacy = Path::new(LEGACY_DB);
+        // 旧数据只导入默认档案
+        if !legacy.exists() || config::get().profile != DEFAULT_PROFILE {
+            return Ok(None);
+        }
+        let current = storage::sled_path(&config::get().data_dir);
+        if legacy.canonicalize()? == current.canonicalize().unwrap_or_default() {
+            return Ok(None);
+        }
+        if import_legacy_into(storage().as_ref(), legacy)? {
+            Ok(Some(legacy.canonicalize()?))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// 插入最高分
     pub fn insert_top_score(score: u128) -> Result<()> {
         let palace_size = unsafe { PALACE_SIZE };
-        DB.insert(format!("{}_top_score", palace_size), &score.to_be_bytes())?;
-        Ok(())
+        storage().insert_top_score(palace_size, score)
     }
 
     /// 获取最高分
     pub fn top_score() -> Result<u128> {
         let palace_size = unsafe { PALACE_SIZE };
-        let top_score = DB.get(format!("{}_top_score", palace_size))?;
-        if let Some(top_score) = top_score {
-            if let Some(&top_score) = slice_as_array!(top_score.as_ref(), [u8; 16]) {
-                return Ok(u128::from_be_bytes(top_score));
-            }
+        Self::top_score_of(palace_size)
+    }
+
+    /// 获取指定模式的最高分
+    pub fn top_score_of(palace_size: usize) -> Result<u128> {
+        storage().top_score(palace_size)
+    }
+
+    /// 指定模式的统计
+    pub fn stats_of(palace_size: usize) -> Result<Stats> {
+        storage().stats(palace_size)
+    }
+
+    /// 把结束的一局记入统计
+    pub fn record_stats(game: &Game) -> Result<()> {
+        let palace_size = game.palaces.len();
+        let mut stats = storage().stats(palace_size)?;
+        stats.record(game);
+        storage().insert_stats(palace_size, &stats)
+    }
+
+    /// 指定模式的排行榜
+    pub fn leaderboard_of(palace_size: usize) -> Result<Vec<Entry>> {
+        storage().leaderboard(palace_size)
+    }
+
+    /// 得分能否进入指定模式的排行榜
+    pub fn qualifies(palace_size: usize, score: u128) -> bool {
+        let entries = storage().leaderboard(palace_size).unwrap_or_default();
+        leaderboard::qualifies(&entries, score)
+    }
+
+    /// 记入排行榜并记住名字，返回名次
+    pub fn insert_leaderboard(palace_size: usize, entry: Entry) -> Result<Option<usize>> {
+        storage().insert_meta(PLAYER_NAME, &entry.name)?;
+        let mut entries = storage().leaderboard(palace_size)?;
+        let rank = leaderboard::insert(&mut entries, entry);
+        if rank.is_some() {
+            storage().insert_leaderboard(palace_size, &entries)?;
+        }
+        Ok(rank)
+    }
+
+    /// 成就进度
+    pub fn achievements() -> Result<Achievements> {
+        storage().achievements()
+    }
+
+    /// 根据事件更新成就进度，返回新达成的成就
+    pub fn update_achievements(event: &Event) -> Result<Vec<&'static Achievement>> {
+        let mut achievements = storage().achievements()?;
+        let before = achievements.clone();
+        let unlocked = achievements.update(event);
+        if achievements != before {
+            storage().insert_achievements(&achievements)?;
         }
-        Ok(0)
+        Ok(unlocked)
     }
 
-    /// 存历史记录
+    /// 上次输入的名字，没有时使用系统用户名
+    pub fn player_name() -> String {
+        storage()
+            .meta(PLAYER_NAME)
+            .ok()
+            .flatten()
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_default()
+    }
+
+    /// 存历史记录，最多保留 `undo_limit` 步
     pub fn insert_history(game: &Game) -> Result<()> {
-        let game_json = serde_json::to_string(game)?;
-        DB.insert(HISTORY, game_json.as_bytes())?;
-        Ok(())
+        let undo_limit = config::get().undo_limit;
+        let history = Self::histories()?;
+        let skip = (history.len() + 1).saturating_sub(undo_limit);
+        let history = history
+            .iter()
+            .chain(std::iter::once(game))
+            .skip(skip)
+            .collect::<Vec<_>>();
+        storage().insert_histories(&history)
     }
 
-    /// 获取上次的状态
-    pub fn history<'a>() -> Result<Option<Game<'a>>> {
-        let history = DB.get(HISTORY)?;
-        if let Some(history) = history {
-            let game = serde_json::from_slice::<Game>(&*history)?;
-            Ok(Some(game))
-        } else {
-            Ok(None)
+    /// 取出上一步的状态
+    pub fn history() -> Result<Option<Game>> {
+        let mut history = Self::histories()?;
+        let game = history.pop();
+        if game.is_some() {
+            storage().insert_histories(&history.iter().collect::<Vec<_>>())?;
         }
+        Ok(game)
     }
 
     /// 删除
     pub fn remove_history() -> Result<()> {
-        DB.remove(HISTORY)?;
-        Ok(())
+        storage().insert_histories(&[])
+    }
+
+    /// 所有历史记录
+    pub fn histories() -> Result<Vec<Game>> {
+        storage().histories()
+    }
+
+    /// 获取未完成的存档
+    pub fn save() -> Result<Option<Replay>> {
+        storage().save()
+    }
+
+    /// 保存未完成的游戏
+    pub fn insert_save(save: &Replay) -> Result<()> {
+        storage().insert_save(save)
+    }
+
+    /// 删除存档
+    pub fn remove_save() -> Result<()> {
+        storage().remove_save()
+    }
+
+    /// 保存最近一局的回放
+    pub fn insert_replay(replay: &Replay) -> Result<()> {
+        storage().insert_replay(replay)
+    }
+
+    /// 获取最近一局的回放
+    pub fn replay() -> Result<Option<Replay>> {
+        storage().replay()
+    }
+
+    /// 导出全部数据
+    pub fn export() -> Result<Archive> {
+        Archive::export(storage().as_ref())
+    }
+
+    /// 导入数据，`replace` 为真时覆盖现有数据
+    pub fn import(archive: &Archive, replace: bool) -> Result<ImportReport> {
+        archive.import_into(storage().as_ref(), replace)
+    }
+
+    /// 将数据写入磁盘
+    pub fn flush() -> Result<()> {
+        storage().flush()
     }
 }
 
+/// 合并旧数据库：最高分取较大值，其它数据不覆盖已有的
+fn import_legacy_into(storage: &dyn Storage, legacy: &Path) -> Result<bool> {
+    if storage.meta(LEGACY_IMPORTED)?.is_some() {
+        return Ok(false);
+    }
+
+    // 旧版本的程序还可能使用这个数据库，不能就地升级
+    storage::snapshot(legacy)?.import_into(storage, false)?;
+
+    storage.insert_meta(LEGACY_IMPORTED, &legacy.to_string_lossy())?;
+    storage.flush()?;
+    Ok(true)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::global::HISTORY;
+    use crate::storage::MemoryStorage;
+    use std::collections::BTreeMap;
+
     #[test]
     fn test() {
-        let game = Game::new();
-
-        let json = serde_json::to_string(&game).unwrap();
+        let game = Game::with_seed(4, 0);
 
-        DB.insert("history", json.as_bytes()).unwrap();
+        Store::insert_history(&game).unwrap();
+        let history = Store::history().unwrap().unwrap();
+        assert_eq!(game.palaces, history.palaces);
+        assert!(Store::history().unwrap().is_none());
 
-        let history = DB.get("history").unwrap().unwrap();
+        Store::insert_replay(&Replay::from_game(&game)).unwrap();
+        assert_eq!(game.seed, Store::replay().unwrap().unwrap().seed);
+    }
 
-        let game = serde_json::from_slice::<Game>(&*history).unwrap();
-        println!("game = {:?}", game);
+    /// 目录下全部文件的内容
+    fn files(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut contents = BTreeMap::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                contents.extend(files(&path));
+            } else {
+                let content = std::fs::read(&path).unwrap();
+                contents.insert(path, content);
+            }
+        }
+        contents
+    }
 
-        let top_score = 2048_u128.to_be_bytes();
+    #[test]
+    fn test_import_legacy() {
+        let dir = std::env::temp_dir().join(format!("t-2048-legacy-{}", std::process::id()));
+        let legacy = dir.join("legacy");
+        {
+            // 旧版本的格式：最高分为大端字节，历史记录为单个 JSON
+            let old = sled::open(&legacy).unwrap();
+            old.insert("4_top_score", &100u128.to_be_bytes()).unwrap();
+            old.insert("5_top_score", &10u128.to_be_bytes()).unwrap();
+            let game = Game::with_seed(4, 0);
+            old.insert(HISTORY, serde_json::to_vec(&game).unwrap())
+                .unwrap();
+            old.flush().unwrap();
+        }
+        let before = files(&legacy);
 
-        DB.insert("top_score", &top_score).unwrap();
+        let storage = MemoryStorage::default();
+        storage.insert_top_score(5, 20).unwrap();
 
-        let top_score = DB.get("top_score").unwrap().unwrap();
+        assert!(import_legacy_into(&storage, &legacy).unwrap());
+        assert!(!import_legacy_into(&storage, &legacy).unwrap());
 
-        let top_score = slice_as_array!(&*top_score, [u8; 16]).unwrap();
+        assert_eq!(100, storage.top_score(4).unwrap());
+        assert_eq!(20, storage.top_score(5).unwrap());
+        assert_eq!(1, storage.histories().unwrap().len());
+        assert_eq!(before, files(&legacy));
 
-        let top_score = u128::from_be_bytes(*top_score);
-        println!("top_score = {}", top_score);
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
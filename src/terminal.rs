@@ -0,0 +1,192 @@
+use std::io;
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+
+/// 收到信号后等待主循环退出的时间
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 进入游戏界面时设置终端，离开作用域时恢复，
+/// 出错返回或 panic 时也能恢复
+pub struct TerminalGuard {
+    /// 收到 SIGINT / SIGTERM / SIGHUP
+    terminated: Arc<AtomicBool>,
+    /// 还在游戏界面中
+    active: Arc<AtomicBool>,
+    /// 使用了备用屏幕
+    fullscreen: bool,
+    /// 注册的信号处理，离开时注销
+    signals: Vec<signal_hook::SigId>,
+    /// 离开时换回进入前的 panic 处理
+    restore_hook: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl TerminalGuard {
+    /// 开启原始模式、备用屏幕和鼠标捕获，并注册 panic 和信号处理
+    ///
+    /// 终端断开后 crossterm 会一直卡在读取输入上，主循环没法检查信号，
+    /// 这时由后台线程调用 `on_hangup` 后直接退出进程
+    pub fn enter(on_hangup: impl FnOnce() + Send + 'static) -> Result<Self> {
+        Self::enter_with(true, on_hangup)
+    }
+
+    /// 逐行模式只开启原始模式，输出留在终端的滚动记录中
+    pub fn enter_line(on_hangup: impl FnOnce() + Send + 'static) -> Result<Self> {
+        Self::enter_with(false, on_hangup)
+    }
+
+    fn enter_with(fullscreen: bool, on_hangup: impl FnOnce() + Send + 'static) -> Result<Self> {
+        // 先开启原始模式，失败时还没有注册任何处理
+        enable_raw_mode()?;
+        let mut guard = Self {
+            terminated: Arc::new(AtomicBool::new(false)),
+            active: Arc::new(AtomicBool::new(true)),
+            fullscreen,
+            signals: Vec::new(),
+            restore_hook: None,
+        };
+
+        // panic 信息要在恢复终端之后输出，否则会被备用屏幕吞掉
+        let hook = Arc::new(panic::take_hook());
+        let previous = Arc::clone(&hook);
+        panic::set_hook(Box::new(move |info| {
+            restore(fullscreen);
+            (*previous)(info);
+        }));
+        guard.restore_hook = Some(Box::new(move || {
+            panic::set_hook(Box::new(move |info| (*hook)(info)));
+        }));
+
+        let mut signals = vec![signal_hook::SIGINT, signal_hook::SIGTERM];
+        #[cfg(unix)]
+        signals.push(signal_hook::SIGHUP);
+        for signal in signals {
+            let id = signal_hook::flag::register(signal, Arc::clone(&guard.terminated))?;
+            guard.signals.push(id);
+        }
+
+        // 离开游戏界面后线程也随之结束
+        let (flag, running) = (Arc::clone(&guard.terminated), Arc::clone(&guard.active));
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                if flag.load(Ordering::Relaxed) {
+                    thread::sleep(EXIT_TIMEOUT);
+                    if running.load(Ordering::Relaxed) {
+                        restore(fullscreen);
+                        on_hangup();
+                        process::exit(1);
+                    }
+                    return;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        if fullscreen {
+            execute!(io::stdout(), EnterAlternateScreen, Hide, EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+
+    /// 是否收到了退出信号
+    pub fn terminated(&self) -> bool {
+        self.terminated.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+        restore(self.fullscreen);
+        for id in self.signals.drain(..) {
+            signal_hook::unregister(id);
+        }
+        // panic 时钩子正在使用中，不能替换
+        if !std::thread::panicking() {
+            if let Some(restore_hook) = self.restore_hook.take() {
+                restore_hook();
+            }
+        }
+    }
+}
+
+/// 恢复终端，忽略错误以便在 panic 时也能调用
+fn restore(fullscreen: bool) {
+    if fullscreen {
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            Show,
+            DisableMouseCapture
+        );
+    }
+    let _ = disable_raw_mode();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 把标准输入换成伪终端，返回主从两端和原来的标准输入
+    fn pty_stdin() -> (libc::c_int, libc::c_int, libc::c_int) {
+        let (mut master, mut slave) = (0, 0);
+        let null = std::ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                0,
+                libc::openpty(&mut master, &mut slave, null, null as _, null as _)
+            );
+            let stdin = libc::dup(libc::STDIN_FILENO);
+            libc::dup2(slave, libc::STDIN_FILENO);
+            (master, slave, stdin)
+        }
+    }
+
+    fn canonical(fd: libc::c_int) -> bool {
+        let mut ios: libc::termios = unsafe { std::mem::zeroed() };
+        unsafe { libc::tcgetattr(fd, &mut ios) };
+        ios.c_lflag & libc::ICANON != 0
+    }
+
+    #[test]
+    fn test_guard() {
+        let (master, slave, stdin) = pty_stdin();
+        let hooked = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&hooked);
+        panic::set_hook(Box::new(move |_| flag.store(true, Ordering::Relaxed)));
+
+        let guard = TerminalGuard::enter_line(|| {}).unwrap();
+        assert!(!canonical(slave));
+        assert!(!guard.terminated());
+        let terminated = Arc::clone(&guard.terminated);
+        drop(guard);
+        assert!(canonical(slave));
+
+        // 信号处理已注销，后台线程已退出，不再持有标记
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(1, Arc::strong_count(&terminated));
+
+        // 换回了进入前的 panic 处理
+        assert!(panic::catch_unwind(|| panic!("test")).is_err());
+        assert!(hooked.load(Ordering::Relaxed));
+
+        let _ = panic::take_hook();
+        unsafe {
+            libc::dup2(stdin, libc::STDIN_FILENO);
+            libc::close(stdin);
+            libc::close(slave);
+            libc::close(master);
+        }
+    }
+}
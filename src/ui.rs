@@ -1,19 +1,69 @@
 use tui::{
     backend::Backend,
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, LineGauge, List, ListItem, ListState, Paragraph,
+        Row, Sparkline, Table, TableState, Widget, Wrap,
+    },
     Frame,
 };
 
 use crate::{
-    global::PALACE_SIZE,
-    palace::{Game, Palace},
+    achievement::{self, Achievements},
+    animation::{lerp, Animation, Confetti, Phase},
+    config::{self, Action, PALACE_SIZES},
+    global, i18n,
+    leaderboard::Entry,
+    palace::{Game, MoveDirection, Palace},
+    stats::Stats,
+    store::Store,
     util,
 };
 
-pub fn create_block(title: &str) -> Block {
+/// 宽度或高度小于这个值时去掉两侧的面板，只显示得分和宫格
+pub const FULL_WIDTH: u16 = 80;
+pub const FULL_HEIGHT: u16 = 20;
+
+/// 紧凑布局中得分栏的高度
+const STATUS_HEIGHT: u16 = 3;
+
+/// 能显示宫格的最小终端大小，每格至少 6 列 3 行
+pub fn min_size(palace_size: usize) -> (u16, u16) {
+    let palace_size = palace_size as u16;
+    (palace_size * 6, palace_size * 3 + STATUS_HEIGHT)
+}
+
+/// 紧凑布局：上方一行得分，下方为宫格
+pub fn compact_layout(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(STATUS_HEIGHT), Constraint::Min(0)])
+        .split(area)
+}
+
+/// 终端太小时代替游戏界面的提示
+pub fn too_small(f: &mut Frame<impl Backend>, (width, height): (u16, u16)) {
+    let size = f.size();
+    let text = format!(
+        "{} {}x{}\n({}x{})",
+        i18n::messages().too_small,
+        width,
+        height,
+        size.width,
+        size.height
+    );
+    let top = size.height.saturating_sub(text.lines().count() as u16) / 2;
+    let area = Rect::new(size.x, size.y + top, size.width, size.height - top);
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+pub fn create_block(title: &str) -> Block<'_> {
     Block::default()
         .title(title)
         .borders(Borders::ALL)
@@ -28,19 +78,6 @@ pub fn crate_percentage_constraint(items: &[u16]) -> Vec<Constraint> {
     constraints
 }
 
-pub fn render_description(
-    f: &mut Frame<impl Backend>,
-    p: &str,
-    title: &str,
-    chunks: &[Rect],
-    index: usize,
-) {
-    let paragraph = Paragraph::new(p)
-        .alignment(Alignment::Left)
-        .block(create_block(title).title(Spans::from(Span::styled(title, Style::default()))));
-    f.render_widget(paragraph, chunks[index]);
-}
-
 pub fn render_paragraph(
     f: &mut Frame<impl Backend>,
     p: &str,
@@ -54,14 +91,31 @@ pub fn render_paragraph(
     f.render_widget(paragraph, chunks[index]);
 }
 
-pub fn render_palace(
-    f: &mut Frame<impl Backend>,
-    chunks: &[Rect],
-    index: usize,
-    palaces: &[Vec<u128>],
-) {
-    let palace_size = unsafe { PALACE_SIZE };
+/// 去掉屏幕上的颜色，有背景色的地方（如选中的行）改为反色
+pub fn monochrome(f: &mut Frame<impl Backend>) {
+    struct Monochrome;
+
+    impl Widget for Monochrome {
+        fn render(self, area: Rect, buf: &mut Buffer) {
+            for y in area.top()..area.bottom() {
+                for x in area.left()..area.right() {
+                    let cell = buf.get_mut(x, y);
+                    if cell.bg != Color::Reset {
+                        cell.modifier.insert(Modifier::REVERSED);
+                    }
+                    cell.fg = Color::Reset;
+                    cell.bg = Color::Reset;
+                }
+            }
+        }
+    }
+
+    let area = f.size();
+    f.render_widget(Monochrome, area);
+}
 
+/// 每一格的位置，不含奇数时用来充满布局的部分
+pub fn palace_rects(area: Rect, palace_size: usize) -> Vec<Vec<Rect>> {
     let psf = (1.0 / palace_size as f64 * 100.0) as u16;
 
     let mut constraints = (0..palace_size).fold(Vec::with_capacity(palace_size), |mut cs, _| {
@@ -77,20 +131,229 @@ pub fn render_palace(
     let middle_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(constraints.clone())
-        .split(chunks[index]);
-
-    for (x, xp) in palaces.iter().enumerate() {
-        let num_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(constraints.clone())
-            .split(middle_chunks[x]);
-        for (y, &yp) in xp.iter().enumerate() {
-            let palace = Palace::default().num(yp);
-            f.render_widget(palace, num_chunks[y]);
+        .split(area);
+
+    (0..palace_size)
+        .map(|x| {
+            let mut row = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(constraints.clone())
+                .split(middle_chunks[x]);
+            row.truncate(palace_size);
+            row
+        })
+        .collect()
+}
+
+/// 宫格，有动画时按动画的阶段绘制
+pub fn render_palace(
+    f: &mut Frame<impl Backend>,
+    chunks: &[Rect],
+    index: usize,
+    palaces: &[Vec<u128>],
+    animation: Option<&Animation>,
+) {
+    let rects = palace_rects(chunks[index], palaces.len());
+
+    let big_digits = config::get().big_digits;
+    let palace = || Palace::default().big_digits(big_digits);
+
+    match animation.map(Animation::phase) {
+        None => {
+            for (x, xp) in palaces.iter().enumerate() {
+                for (y, &yp) in xp.iter().enumerate() {
+                    f.render_widget(palace().num(yp), rects[x][y]);
+                }
+            }
+        }
+        // 先画空的格子，再把数字方块画在移动路径上
+        Some(Phase::Slide { slides, t }) => {
+            for &rect in rects.iter().flatten() {
+                f.render_widget(palace(), rect);
+            }
+            for slide in slides {
+                let from = rects[slide.from.0][slide.from.1];
+                let to = rects[slide.to.0][slide.to.1];
+                f.render_widget(palace().num(slide.num), lerp(from, to, t));
+            }
+        }
+        // 新数字淡入，合并出的数字前半段放大一圈
+        Some(Phase::Settle { merged, spawned, t }) => {
+            for (x, xp) in palaces.iter().enumerate() {
+                for (y, &yp) in xp.iter().enumerate() {
+                    let fade = if spawned == Some((x, y)) { t } else { 1.0 };
+                    f.render_widget(palace().num(yp).fade(fade), rects[x][y]);
+                }
+            }
+            if t < 0.5 {
+                let board = chunks[index];
+                for &(x, y) in merged {
+                    let rect = rects[x][y];
+                    let left = rect.x.saturating_sub(2).max(board.x);
+                    let top = rect.y.saturating_sub(1).max(board.y);
+                    let right = (rect.right() + 2).min(board.right());
+                    let bottom = (rect.bottom() + 1).min(board.bottom());
+                    let pop = Rect::new(left, top, right - left, bottom - top);
+                    f.render_widget(palace().num(palaces[x][y]), pop);
+                }
+            }
         }
     }
 }
 
+/// 得分走势、上一步的得分、离下一个目标的进度和各数字的个数
+pub fn render_progress(f: &mut Frame<impl Backend>, chunks: &[Rect], index: usize, game: &Game) {
+    let messages = i18n::messages();
+    let block = create_block(messages.trend);
+    let area = block.inner(chunks[index]);
+    f.render_widget(block, chunks[index]);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    // 宽度不够时只显示最近的几步
+    let scores = game
+        .scores
+        .iter()
+        .map(|&score| score.min(u64::MAX as u128) as u64)
+        .collect::<Vec<_>>();
+    let skip = scores.len().saturating_sub(rows[0].width as usize);
+    let sparkline = Sparkline::default()
+        .data(&scores[skip..])
+        .style(Style::default().fg(Color::LightGreen));
+    f.render_widget(sparkline, rows[0]);
+
+    let gain = format!("{}  +{}", messages.last_gain, game.last_gain());
+    f.render_widget(Paragraph::new(gain), rows[1]);
+
+    // 按指数计算进度，每合成一级前进相同的距离
+    let (max_tile, target) = (game.max_tile(), game.target_tile());
+    let exponent = |num: u128| (127 - num.max(1).leading_zeros()) as f64;
+    let gauge = LineGauge::default()
+        .ratio(exponent(max_tile) / exponent(target))
+        .label(format!("{} / {}", max_tile, target))
+        .gauge_style(Style::default().fg(Color::Yellow));
+    f.render_widget(gauge, rows[2]);
+
+    let counts = game
+        .tile_counts()
+        .iter()
+        .map(|(num, count)| format!("{}×{}", num, count))
+        .collect::<Vec<_>>()
+        .join("  ");
+    f.render_widget(Paragraph::new(counts).wrap(Wrap { trim: true }), rows[3]);
+}
+
+/// 得分后在得分栏右侧浮起的 “+N”，`t` 为 0 到 1 的进度
+pub fn score_gain(f: &mut Frame<impl Backend>, chunks: &[Rect], index: usize, gain: u128, t: f64) {
+    let area = chunks[index];
+    if area.height < 3 || area.width < 4 {
+        return;
+    }
+    // 从分数下方一行升到边框上
+    let rise = ((t * 3.0) as u16).min(2);
+    let y = area.y + 2 - rise;
+    let text = format!("+{}", gain);
+    let width = (Span::raw(text.as_str()).width() as u16).min(area.width - 2);
+    let style = Style::default()
+        .fg(util::fade(Color::Rgb(120, 230, 120), 1.0 - t * t))
+        .add_modifier(Modifier::BOLD);
+    let rect = Rect::new(area.right() - 1 - width, y, width, 1);
+    f.render_widget(Paragraph::new(Span::styled(text, style)), rect);
+}
+
+/// 打破最高分的庆祝，`confetti` 为是否洒彩纸
+pub fn celebration(f: &mut Frame<impl Backend>, score: u128, t: f64, confetti: bool) {
+    let size = f.size();
+    if confetti {
+        // 每 100 毫秒换一次位置，越到后面越稀疏
+        let seed = (t * 25.0) as u64;
+        f.render_widget(Confetti::new(seed, 0.06 * (1.0 - t)), size);
+    }
+
+    let messages = i18n::messages();
+    let text = format!("★ {}  {} ★", messages.best_beaten, score);
+    let width = (Span::raw(text.as_str()).width() as u16 + 6).min(size.width);
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y,
+        width,
+        3.min(size.height),
+    );
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(create_block(messages.top_score));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// 侧栏中可以点击的按钮
+const BUTTONS: [Action; 3] = [Action::Restart, Action::Undo, Action::Hint];
+
+/// 方向对应的箭头
+pub fn arrow(md: MoveDirection) -> &'static str {
+    match md {
+        MoveDirection::Up => "↑",
+        MoveDirection::Down => "↓",
+        MoveDirection::Left => "←",
+        MoveDirection::Right => "→",
+    }
+}
+
+/// 每行一个按钮，放不下的不画，返回每个按钮的位置；`hint` 为提示的方向，显示在提示按钮上
+pub fn render_buttons(
+    f: &mut Frame<impl Backend>,
+    area: Rect,
+    hint: Option<MoveDirection>,
+) -> Vec<(Rect, Action)> {
+    let messages = i18n::messages();
+    let style = Style::default()
+        .fg(Color::White)
+        .bg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD);
+    // 高度够时按钮之间空一行
+    let step = if area.height as usize >= BUTTONS.len() * 2 - 1 {
+        2
+    } else {
+        1
+    };
+    BUTTONS
+        .iter()
+        .enumerate()
+        .take_while(|&(i, _)| i as u16 * step < area.height)
+        .map(|(i, &action)| {
+            let label = match hint.filter(|_| action == Action::Hint) {
+                Some(md) => format!(" {} {} ", messages.action(action), arrow(md)),
+                None => format!(" {} ", messages.action(action)),
+            };
+            let width = (Span::raw(label.as_str()).width() as u16).min(area.width);
+            let rect = Rect::new(
+                area.x + (area.width - width) / 2,
+                area.y + i as u16 * step,
+                width,
+                1,
+            );
+            f.render_widget(Paragraph::new(Span::styled(label, style)), rect);
+            (rect, action)
+        })
+        .collect()
+}
+
 pub fn render_model(f: &mut Frame<impl Backend>, chunks: &[Rect], index: usize, game: &mut Game) {
     let items = game
         .model
@@ -100,7 +363,11 @@ pub fn render_model(f: &mut Frame<impl Backend>, chunks: &[Rect], index: usize,
         .collect::<Vec<_>>();
 
     let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("模式"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(i18n::messages().model),
+        )
         .highlight_style(
             Style::default()
                 .bg(Color::LightGreen)
@@ -112,10 +379,14 @@ pub fn render_model(f: &mut Frame<impl Backend>, chunks: &[Rect], index: usize,
 }
 
 pub fn game_over_popup(f: &mut Frame<impl Backend>, total_score: u128) {
-    let paragraph = Paragraph::new(format!("得分  {}", total_score))
+    let messages = i18n::messages();
+    let paragraph = Paragraph::new(format!("{}  {}", messages.score, total_score))
         .alignment(Alignment::Center)
         .block(
-            create_block("游戏结束").title(Spans::from(Span::styled("游戏结束", Style::default()))),
+            create_block(messages.game_over).title(Spans::from(Span::styled(
+                messages.game_over,
+                Style::default(),
+            ))),
         );
 
     let area = centered_rect(60, 20, f.size());
@@ -123,6 +394,389 @@ pub fn game_over_popup(f: &mut Frame<impl Backend>, total_score: u128) {
     f.render_widget(paragraph, area);
 }
 
+/// 各模式的统计，以及当前模式的最大数字分布；`stats` 与 `PALACE_SIZES` 一一对应
+pub fn stats_popup(f: &mut Frame<impl Backend>, stats: &[Stats], palace_size: usize) {
+    let messages = i18n::messages();
+
+    let row = |name: &'static str, value: &dyn Fn(&Stats) -> String| {
+        let mut cells = vec![Cell::from(name)];
+        cells.extend(stats.iter().map(|s| Cell::from(value(s))));
+        Row::new(cells)
+    };
+    let rows = vec![
+        row(messages.games, &|s| s.games.to_string()),
+        row(messages.wins, &|s| s.wins.to_string()),
+        row(messages.win_rate, &|s| format!("{:.1}%", s.win_rate())),
+        row(messages.average_score, &|s| s.average_score().to_string()),
+        row(messages.median_score, &|s| s.median_score().to_string()),
+        row(messages.longest_game, &|s| s.longest_game.to_string()),
+        row(messages.total_moves, &|s| s.total_moves.to_string()),
+        row(messages.highest_tile, &|s| s.highest_tile.to_string()),
+    ];
+    let mut header = vec![Cell::from("")];
+    header.extend(
+        PALACE_SIZES
+            .iter()
+            .map(|size| Cell::from(format!("{} * {}", size, size))),
+    );
+    let widths = [Constraint::Percentage(20); 5];
+    let table = Table::new(rows)
+        .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(create_block(messages.stats))
+        .widths(&widths);
+
+    let current = PALACE_SIZES
+        .iter()
+        .position(|&size| size == palace_size)
+        .map(|i| &stats[i]);
+    let distribution = current
+        .map(|s| s.max_tiles())
+        .unwrap_or_default()
+        .iter()
+        .map(|(tile, count)| format!("{:>6}  {}", tile, count))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let title = format!("{} ({} * {})", messages.max_tiles, palace_size, palace_size);
+    let paragraph = Paragraph::new(distribution).block(create_block(&title));
+
+    let area = centered_rect(70, 70, f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(11), Constraint::Min(0)].as_ref())
+        .split(area);
+    f.render_widget(Clear, area);
+    f.render_widget(table, chunks[0]);
+    f.render_widget(paragraph, chunks[1]);
+}
+
+/// 指定模式的排行榜，`highlight` 为刚记入的名次
+pub fn leaderboard_popup(
+    f: &mut Frame<impl Backend>,
+    palace_size: usize,
+    entries: &[Entry],
+    highlight: Option<usize>,
+) {
+    let messages = i18n::messages();
+
+    let rows = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let duration = match e.duration {
+                0 => "-".to_string(),
+                d => util::duration(d),
+            };
+            Row::new(vec![
+                (i + 1).to_string(),
+                e.name.clone(),
+                e.score.to_string(),
+                e.max_tile.to_string(),
+                e.moves.to_string(),
+                duration,
+                util::date(e.date),
+                e.seed.to_string(),
+                if e.undo_used { "✓" } else { "" }.to_string(),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let header = Row::new(vec![
+        "#",
+        messages.name,
+        messages.score,
+        messages.highest_tile,
+        messages.moves,
+        messages.duration,
+        messages.date,
+        messages.seed,
+        messages.undo,
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let widths = [
+        Constraint::Length(3),
+        Constraint::Percentage(18),
+        Constraint::Percentage(10),
+        Constraint::Percentage(10),
+        Constraint::Percentage(8),
+        Constraint::Percentage(8),
+        Constraint::Length(10),
+        Constraint::Percentage(22),
+        Constraint::Length(6),
+    ];
+    let title = format!(
+        "{} ({} * {})",
+        messages.leaderboard, palace_size, palace_size
+    );
+    let table = Table::new(rows)
+        .header(header)
+        .block(create_block(&title))
+        .widths(&widths)
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut state = TableState::default();
+    state.select(highlight);
+    let area = centered_rect(80, 60, f.size());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// 帮助的内容：当前的按键绑定和鼠标操作
+pub fn help_text() -> String {
+    let messages = i18n::messages();
+    format!(
+        "{}4. {}\n   {}\n",
+        global::game_description(),
+        messages.mouse,
+        messages.mouse_help
+    )
+}
+
+/// 帮助，从第 `scroll` 行开始显示
+pub fn help_popup(f: &mut Frame<impl Backend>, scroll: u16) {
+    let messages = i18n::messages();
+    let title = format!("{} · {}", messages.description, messages.help_scroll);
+    let paragraph = Paragraph::new(help_text())
+        .block(create_block(&title))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+
+    let area = centered_rect(60, 70, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// 确认后执行的操作
+#[derive(Debug, Clone, Copy)]
+pub enum Pending {
+    Action(Action),
+    /// 点击模式列表选中的模式
+    Model(usize),
+}
+
+impl Pending {
+    /// 重新开始和切换模式在有进行中的一局时确认，退出时总是确认，可以在配置中关闭
+    pub fn needs_confirm(self, game: &Game) -> bool {
+        let playing = !game.steps.is_empty() && !game.game_over();
+        config::get().confirm
+            && match self {
+                Pending::Action(Action::Quit) => true,
+                Pending::Action(Action::Restart | Action::NextModel | Action::PreviousModel)
+                | Pending::Model(_) => playing,
+                Pending::Action(_) => false,
+            }
+    }
+
+    /// 对话框中的问题
+    pub fn question(self) -> &'static str {
+        let messages = i18n::messages();
+        match self {
+            Pending::Action(Action::Quit) => messages.confirm_quit,
+            Pending::Action(Action::Restart) => messages.confirm_restart,
+            _ => messages.confirm_model,
+        }
+    }
+}
+
+/// 确认对话框，`question` 为要确认的问题
+pub fn confirm_popup(f: &mut Frame<impl Backend>, question: &str) {
+    let messages = i18n::messages();
+    let text = format!("{}\n\n{}", question, messages.confirm_keys);
+    let size = f.size();
+    let width = Span::raw(question)
+        .width()
+        .max(Span::raw(messages.confirm_keys).width());
+    let width = (width as u16 + 6).min(size.width);
+    let height = 6.min(size.height);
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    );
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(create_block(messages.confirm))
+        .wrap(Wrap { trim: true });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// 暂停时盖住宫格的提示
+pub fn paused_popup(f: &mut Frame<impl Backend>, area: Rect) {
+    let messages = i18n::messages();
+    let height = 3.min(area.height);
+    let rect = Rect::new(
+        area.x,
+        area.y + (area.height - height) / 2,
+        area.width,
+        height,
+    );
+    let paragraph = Paragraph::new(messages.paused)
+        .alignment(Alignment::Center)
+        .block(create_block(messages.pause));
+    f.render_widget(paragraph, rect);
+}
+
+/// 左侧的当前模式统计，标题中提示打开帮助的按键
+pub fn render_stats(f: &mut Frame<impl Backend>, area: Rect, stats: &Stats) {
+    let messages = i18n::messages();
+    let config = config::get();
+    let mut text = format!(
+        "{}  {}\n{}  {}\n{}  {:.1}%\n{}  {}\n{}  {}",
+        messages.games,
+        stats.games,
+        messages.wins,
+        stats.wins,
+        messages.win_rate,
+        stats.win_rate(),
+        messages.average_score,
+        stats.average_score(),
+        messages.highest_tile,
+        stats.highest_tile
+    );
+    if Store::is_locked() {
+        text = format!("{}\n\n{}", messages.storage_locked, text);
+    }
+    let title = match config.keys.keys(Action::Help).first() {
+        Some(&key) => format!(
+            "{} · {} {}",
+            messages.stats,
+            config::key_name(key),
+            messages.help
+        ),
+        None => messages.stats.to_string(),
+    };
+    let paragraph = Paragraph::new(text)
+        .block(create_block(&title))
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+/// 全部成就，未达成的显示进度
+pub fn achievements_popup(f: &mut Frame<impl Backend>, achievements: &Achievements) {
+    let messages = i18n::messages();
+
+    let rows = achievement::ALL
+        .iter()
+        .map(|a| {
+            let (name, description) = messages.achievement(a.id);
+            let (mark, status, style) = match achievements.unlocked(a) {
+                Some(date) => ("★", util::date(date), Style::default().fg(Color::Yellow)),
+                None => (
+                    "",
+                    format!("{} / {}", achievements.progress(a), a.target),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            };
+            Row::new(vec![
+                mark.to_string(),
+                name.to_string(),
+                description.to_string(),
+                status,
+            ])
+            .style(style)
+        })
+        .collect::<Vec<_>>();
+    let widths = [
+        Constraint::Length(2),
+        Constraint::Percentage(25),
+        Constraint::Percentage(45),
+        Constraint::Percentage(25),
+    ];
+    let table = Table::new(rows)
+        .block(create_block(messages.achievements))
+        .widths(&widths);
+
+    let area = centered_rect(70, 50, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+/// 右上角依次显示的提示
+pub fn toasts(f: &mut Frame<impl Backend>, toasts: &[String]) {
+    let title = i18n::messages().achievement_unlocked;
+    let size = f.size();
+    let mut y = size.y;
+    for text in toasts {
+        let width = Span::raw(text.as_str())
+            .width()
+            .max(Span::raw(title).width());
+        let width = (width as u16 + 6).min(size.width);
+        let area = Rect::new(size.right() - width, y, width, 3);
+        if area.bottom() > size.bottom() {
+            break;
+        }
+        let paragraph = Paragraph::new(text.as_str())
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Yellow))
+            .block(create_block(title));
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+        y += 3;
+    }
+}
+
+/// 档案列表，最后一项为新建档案；`input` 为正在输入的新档案名
+pub fn profiles_popup(
+    f: &mut Frame<impl Backend>,
+    items: &[String],
+    current: &str,
+    selected: usize,
+    input: Option<&str>,
+) {
+    let messages = i18n::messages();
+    let area = centered_rect(40, 50, f.size());
+    f.render_widget(Clear, area);
+
+    if let Some(name) = input {
+        let paragraph = Paragraph::new(format!("\n{}: {}_", messages.profile_name, name))
+            .alignment(Alignment::Center)
+            .block(create_block(messages.new_profile));
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let mut list = items
+        .iter()
+        .map(|name| {
+            let mark = if name == current { "* " } else { "  " };
+            ListItem::new(format!("{}{}", mark, name))
+        })
+        .collect::<Vec<_>>();
+    list.push(ListItem::new(messages.new_profile));
+    let list = List::new(list)
+        .block(create_block(messages.profiles))
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// 进入排行榜时输入名字
+pub fn name_prompt(f: &mut Frame<impl Backend>, name: &str, score: u128) {
+    let messages = i18n::messages();
+    let text = format!(
+        "{}  {}\n\n{}: {}_\n\n{}",
+        messages.score, score, messages.name, name, messages.enter_name
+    );
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(create_block(messages.new_record));
+
+    let area = centered_rect(60, 30, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -148,3 +802,115 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         )
         .split(popup_layout[1])[1]
 }
+
+/// 以纯文本形式输出宫格
+pub fn board_text(palaces: &[Vec<u128>]) -> String {
+    let width = palaces
+        .iter()
+        .flatten()
+        .map(|num| num.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    palaces
+        .iter()
+        .map(|xp| {
+            xp.iter()
+                .map(|&num| {
+                    let text = if num == 0 {
+                        ".".to_string()
+                    } else {
+                        num.to_string()
+                    };
+                    format!("{:>width$}", text, width = width)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+
+    /// 画出来的文字，去掉空格以免宽字符后的占位影响比较
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect::<String>()
+            .replace(' ', "")
+    }
+
+    #[test]
+    fn test_overlays() {
+        let messages = i18n::messages();
+        let mut terminal = Terminal::new(TestBackend::new(60, 30)).unwrap();
+        terminal.draw(|f| help_popup(f, 0)).unwrap();
+        let text = screen(&terminal);
+        assert!(text.contains(&messages.description.replace(' ', "")));
+        assert!(text.contains(&format!("1.{}", messages.change_model.replace(' ', ""))));
+
+        let area = Rect::new(0, 0, 60, 30);
+        terminal.draw(|f| paused_popup(f, area)).unwrap();
+        let text = screen(&terminal);
+        assert!(text.contains(&messages.paused.replace(' ', "")));
+        assert!(!text.contains(&messages.change_model.replace(' ', "")));
+
+        // 表格的列按比例分配宽度，太窄时各列的宽度不确定
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        let mut stats = vec![Stats::default(); PALACE_SIZES.len()];
+        stats[1].games = 12345;
+        terminal.draw(|f| stats_popup(f, &stats, 4)).unwrap();
+        assert!(screen(&terminal).contains("12345"));
+
+        let entries = vec![Entry {
+            name: "bob".to_string(),
+            score: 4096,
+            ..Entry::default()
+        }];
+        terminal
+            .draw(|f| leaderboard_popup(f, 4, &entries, Some(0)))
+            .unwrap();
+        let text = screen(&terminal);
+        assert!(text.contains("bob"));
+        assert!(text.contains("4096"));
+
+        terminal
+            .draw(|f| achievements_popup(f, &Achievements::default()))
+            .unwrap();
+        let (name, _) = messages.achievement(achievement::ALL[0].id);
+        assert!(screen(&terminal).contains(&name.replace(' ', "")));
+    }
+
+    #[test]
+    fn test_needs_confirm() {
+        let restart = Pending::Action(Action::Restart);
+        let mut game = Game::with_seed(4, 7);
+        assert!(!restart.needs_confirm(&game));
+        assert!(!Pending::Model(0).needs_confirm(&game));
+        assert!(Pending::Action(Action::Quit).needs_confirm(&game));
+
+        game.palaces = vec![vec![2, 2, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]];
+        game.move_palaces(MoveDirection::Left);
+        assert!(restart.needs_confirm(&game));
+        assert!(Pending::Action(Action::NextModel).needs_confirm(&game));
+        assert!(Pending::Model(0).needs_confirm(&game));
+        assert!(!Pending::Action(Action::Hint).needs_confirm(&game));
+
+        game.palaces = vec![
+            vec![2, 4, 2, 4],
+            vec![4, 2, 4, 2],
+            vec![2, 4, 2, 4],
+            vec![4, 2, 4, 2],
+        ];
+        assert!(!restart.needs_confirm(&game));
+        assert!(!Pending::Model(0).needs_confirm(&game));
+    }
+}
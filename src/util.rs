@@ -1,10 +1,19 @@
-use crate::global::{NUM_COLOR, PALACE_SIZE};
-use rand::Rng;
-use tui::style::Color;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{self, Theme};
+use crate::global::{COLORBLIND_COLOR, HIGH_CONTRAST_COLOR, NUM_COLOR};
+use crate::raster;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tui::style::{Color, Modifier};
+
+/// 以随机种子初始化的随机数生成器
+pub fn rng() -> StdRng {
+    StdRng::from_entropy()
+}
 
 /// 90% => 2, 10% => 4
-pub fn two_or_four() -> u128 {
-    let mut rtr = rand::thread_rng();
+pub fn two_or_four(rtr: &mut impl Rng) -> u128 {
     if rtr.gen_range(0..10) < 9 {
         2
     } else {
@@ -13,9 +22,7 @@ pub fn two_or_four() -> u128 {
 }
 
 /// 生成坐标
-pub fn position() -> (usize, usize) {
-    let palace_size = unsafe { PALACE_SIZE };
-    let mut rtr = rand::thread_rng();
+pub fn position(palace_size: usize, rtr: &mut impl Rng) -> (usize, usize) {
     let x = rtr.gen_range(0..palace_size);
     let y = rtr.gen_range(0..palace_size);
     (x, y)
@@ -28,9 +35,94 @@ pub fn color() -> Color {
     NUM_COLOR[index]
 }
 
+/// 数字对应的颜色，随主题变化
+pub fn num_color(num: u128) -> Color {
+    let exponent = 127 - num.max(1).leading_zeros() as usize;
+    let cycle = |colors: &[Color]| colors[exponent.saturating_sub(1) % colors.len()];
+    match config::get().theme {
+        Theme::Random => color(),
+        Theme::Classic => NUM_COLOR[exponent % NUM_COLOR.len()],
+        Theme::HighContrast => cycle(&HIGH_CONTRAST_COLOR),
+        Theme::Colorblind => cycle(&COLORBLIND_COLOR),
+        // 黑白主题只在图片中用到颜色，数字越大越亮
+        Theme::Mono => {
+            let level = 60 + (exponent as u8 % 12) * 16;
+            Color::Rgb(level, level, level)
+        }
+    }
+}
+
+/// 在背景色上看得清的文字颜色
+pub fn text_color(background: Color) -> Color {
+    let [r, g, b] = raster::text_color(raster::rgb(background));
+    Color::Rgb(r, g, b)
+}
+
+/// 黑白主题下数字方块的样式和底纹，按数字大小依次变化
+pub fn mono_style(num: u128) -> (Modifier, &'static str) {
+    const STYLES: [(Modifier, &str); 11] = [
+        (Modifier::empty(), " "),
+        (Modifier::BOLD, " "),
+        (Modifier::empty(), "·"),
+        (Modifier::empty(), "░"),
+        (Modifier::empty(), "▒"),
+        (Modifier::REVERSED, " "),
+        (Modifier::REVERSED, "·"),
+        (Modifier::empty(), "▓"),
+        (Modifier::REVERSED, "░"),
+        (Modifier::REVERSED, "▒"),
+        (Modifier::BOLD, "#"),
+    ];
+    let exponent = 127 - num.max(1).leading_zeros() as usize;
+    STYLES[exponent.saturating_sub(1) % STYLES.len()]
+}
+
+/// 从背景色渐变到指定颜色，`amount` 为 0 到 1；非 RGB 颜色过半后才显示
+pub fn fade(color: Color, amount: f64) -> Color {
+    match color {
+        Color::Rgb(r, g, b) => {
+            let mix = |c: u8| (c as f64 * amount.clamp(0.0, 1.0)).round() as u8;
+            Color::Rgb(mix(r), mix(g), mix(b))
+        }
+        _ if amount < 0.5 => Color::Reset,
+        color => color,
+    }
+}
+
+/// 当前时间（Unix 秒）
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Unix 秒转换为 UTC 日期，如 `2021-03-01`
+pub fn date(secs: u64) -> String {
+    // 见 http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 用时（秒）的显示形式，如 `3:07`、`1:02:03`
+pub fn duration(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60),
+        s => format!("{}:{:02}", s / 60, s % 60),
+    }
+}
+
 /// 判断是否是奇数
 pub fn odd(n: usize) -> bool {
-    n % 2 != 0
+    n % 2 == 1
 }
 
 #[cfg(test)]
@@ -39,8 +131,9 @@ mod test {
     #[test]
     fn test_two_or_four() {
         let all = 100000;
+        let mut rtr = rng();
         let nums = (1..=all).fold(Vec::with_capacity(all), |mut nums, _| {
-            nums.push(two_or_four());
+            nums.push(two_or_four(&mut rtr));
             nums
         });
         let num_2 = nums.iter().filter(|&&num| num == 2).count();
@@ -48,4 +141,30 @@ mod test {
         let num_4 = nums.iter().filter(|&&num| num == 4).count();
         println!("num_4 = {} %", num_4 as f64 / all as f64 * 100.0);
     }
+
+    #[test]
+    fn test_date() {
+        assert_eq!("1970-01-01", date(0));
+        assert_eq!("2000-02-29", date(951_782_400));
+        assert_eq!("2021-03-01", date(1_614_556_800 + 3600));
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!("0:00", duration(0));
+        assert_eq!("3:07", duration(187));
+        assert_eq!("1:02:03", duration(3723));
+    }
+
+    #[test]
+    fn test_mono_style() {
+        // 2 到 2048 的样式各不相同
+        let styles = (1..=11).map(|e| mono_style(1 << e)).collect::<Vec<_>>();
+        for (i, style) in styles.iter().enumerate() {
+            assert!(!styles[i + 1..].contains(style));
+        }
+        assert_eq!(mono_style(2), mono_style(4096));
+        assert_eq!(Color::Rgb(40, 40, 40), text_color(Color::Rgb(255, 255, 0)));
+        assert_eq!(Color::Rgb(255, 255, 255), text_color(Color::Rgb(0, 0, 170)));
+    }
 }